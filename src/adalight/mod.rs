@@ -0,0 +1,226 @@
+#![cfg(feature = "adalight_impl")]
+//! Adalight serial frames, as sent by Hyperion, Prismatik and other ambient lighting software.
+//!
+//! A frame is `Ada`, the big endian LED count minus one, checksum of both count bytes xor `0x55`,
+//! followed by colours of every LED. Frames carry no length of their own beyond the LED count,
+//! so the decoder resynchronises on the next `Ada` after anything it does not understand.
+use crate::{Command, CommandError};
+
+pub const MAGIC: &[u8; 3] = b"Ada";
+pub const HEADER_LEN: usize = 6;
+/// Most LEDs the count field describes
+pub const MAX_LEDS: usize = u16::MAX as usize + 1;
+
+/// Header announcing `led_count` LEDs, fails with `ValueOutOfRange` for 0 or more than [`MAX_LEDS`]
+pub fn header(led_count: usize) -> Result<[u8; HEADER_LEN], CommandError> {
+    if led_count == 0 || led_count > MAX_LEDS {
+        return Err(CommandError::ValueOutOfRange);
+    }
+    let [high, low] = ((led_count - 1) as u16).to_be_bytes();
+    Ok([MAGIC[0], MAGIC[1], MAGIC[2], high, low, high ^ low ^ 0x55])
+}
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Writes `Stream` or `Constant` as Adalight frame, returns number of bytes written.
+    ///
+    /// Fails with `InvalidHeader` for `Health` and `Pulse`, which have no Adalight counterpart,
+    /// `MalformedPayload` for payloads that are not whole pixels and `ValueOutOfRange` for no LEDs
+    pub fn try_write_adalight(&self, buf: &mut [u8]) -> Result<usize, CommandError> {
+        let frame = buf
+            .get_mut(HEADER_LEN..)
+            .ok_or(CommandError::BufferTooSmall)?;
+        let len = self.write_frame(frame)?;
+        buf[..HEADER_LEN].copy_from_slice(&header(len / 3)?);
+        Ok(HEADER_LEN + len)
+    }
+}
+
+/// Decodes Adalight frames byte by byte into `B`, e.g. straight from a UART interrupt
+#[derive(Debug, Clone)]
+pub struct AdalightDecoder<B> {
+    buf: B,
+    header: [u8; HEADER_LEN],
+    header_len: usize,
+    /// Payload length of the current frame
+    expected: usize,
+    received: usize,
+    /// Current frame does not fit into `buf` and is dropped
+    skipping: bool,
+}
+
+impl<B> AdalightDecoder<B>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Frames of up to `buf.len() / 3` LEDs are decoded
+    pub fn new(buf: B) -> Self {
+        Self {
+            buf,
+            header: [0; HEADER_LEN],
+            header_len: 0,
+            expected: 0,
+            received: 0,
+            skipping: false,
+        }
+    }
+
+    /// Forgets a partially received frame
+    pub fn reset(&mut self) {
+        self.header_len = 0;
+        self.skipping = false;
+    }
+
+    /// Feeds the next byte, returns the `Stream` it completes.
+    ///
+    /// Fails with `InvalidHeader` on a checksum mismatch and with `BufferTooSmall` when the announced
+    /// frame does not fit into the buffer, its payload is then skipped.
+    pub fn push(&mut self, byte: u8) -> Result<Option<Command<&[u8]>>, CommandError> {
+        if self.header_len < HEADER_LEN {
+            if self.header_len < MAGIC.len() && byte != MAGIC[self.header_len] {
+                self.header_len = usize::from(byte == MAGIC[0]);
+                return Ok(None);
+            }
+            self.header[self.header_len] = byte;
+            self.header_len += 1;
+            if self.header_len < HEADER_LEN {
+                return Ok(None);
+            }
+
+            let [_, _, _, high, low, checksum] = self.header;
+            if high ^ low ^ 0x55 != checksum {
+                self.header_len = 0;
+                return Err(CommandError::InvalidHeader);
+            }
+            self.expected = (u16::from_be_bytes([high, low]) as usize + 1) * 3;
+            self.received = 0;
+            self.skipping = self.expected > self.buf.as_ref().len();
+            return if self.skipping {
+                Err(CommandError::BufferTooSmall)
+            } else {
+                Ok(None)
+            };
+        }
+
+        if !self.skipping {
+            self.buf.as_mut()[self.received] = byte;
+        }
+        self.received += 1;
+        if self.received < self.expected {
+            return Ok(None);
+        }
+        self.header_len = 0;
+        if self.skipping {
+            self.skipping = false;
+            Ok(None)
+        } else {
+            Ok(Some(Command::Stream(&self.buf.as_ref()[..self.expected])))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{header, AdalightDecoder, HEADER_LEN};
+    use crate::{Command, CommandError};
+    extern crate std;
+    use std::vec::Vec;
+
+    /// Synthetic frame laid out by hand from the Adalight header format, not a capture. Two LEDs, red and blue
+    const SPEC_FRAME: [u8; 12] = [b'A', b'd', b'a', 0x00, 0x01, 0x54, 255, 0, 0, 0, 0, 255];
+
+    fn decode_all<B: AsRef<[u8]> + AsMut<[u8]>>(
+        decoder: &mut AdalightDecoder<B>,
+        bytes: &[u8],
+    ) -> Vec<Result<Vec<u8>, CommandError>> {
+        bytes
+            .iter()
+            .filter_map(|byte| match decoder.push(*byte) {
+                Ok(Some(Command::Stream(frame))) => Some(Ok(frame.to_vec())),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn writes_header() {
+        assert_eq!(header(2), Ok([b'A', b'd', b'a', 0, 1, 0x54]));
+        assert_eq!(header(300), Ok([b'A', b'd', b'a', 1, 0x2b, 0x7f]));
+        assert_eq!(header(0), Err(CommandError::ValueOutOfRange));
+        assert_eq!(header(65_537), Err(CommandError::ValueOutOfRange));
+    }
+
+    #[test]
+    fn encodes_stream_and_constant() {
+        let mut buf = [0_u8; 16];
+        let cmd = Command::Stream([255_u8, 0, 0, 0, 0, 255].as_ref());
+        assert_eq!(cmd.try_write_adalight(&mut buf), Ok(12));
+        assert_eq!(buf[..12], SPEC_FRAME);
+        let cmd: Command<&[u8]> = Command::Constant {
+            led_count: 3,
+            colour: (1, 2, 3),
+        };
+        assert_eq!(cmd.try_write_adalight(&mut buf), Ok(15));
+        assert_eq!(buf[HEADER_LEN..15], [1, 2, 3, 1, 2, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn given_unsupported_command_encoding_fails() {
+        let mut buf = [0_u8; 16];
+        let outcome = Command::<&[u8]>::Health.try_write_adalight(&mut buf);
+        assert_eq!(outcome, Err(CommandError::InvalidHeader));
+        let outcome = Command::Stream([0_u8; 4].as_ref()).try_write_adalight(&mut buf);
+        assert_eq!(outcome, Err(CommandError::MalformedPayload));
+        let outcome = Command::Stream([0_u8; 12].as_ref()).try_write_adalight(&mut buf);
+        assert_eq!(outcome, Err(CommandError::BufferTooSmall));
+        let outcome = Command::Stream([0_u8; 0].as_ref()).try_write_adalight(&mut buf);
+        assert_eq!(outcome, Err(CommandError::ValueOutOfRange));
+    }
+
+    #[test]
+    fn decodes_frames_after_noise() {
+        let mut decoder = AdalightDecoder::new([0_u8; 6]);
+        let mut bytes = Vec::from(&b"xAAd"[..]);
+        bytes.extend_from_slice(&SPEC_FRAME);
+        bytes.extend_from_slice(&SPEC_FRAME);
+        let frames = decode_all(&mut decoder, &bytes);
+        assert_eq!(
+            frames,
+            [Ok(SPEC_FRAME[6..].to_vec()), Ok(SPEC_FRAME[6..].to_vec())]
+        );
+    }
+
+    #[test]
+    fn given_bad_frames_resynchronises() {
+        let mut decoder = AdalightDecoder::new([0_u8; 6]);
+        let mut bytes = Vec::from(&b"Ada\x00\x01\x00"[..]);
+        // Three LEDs do not fit, their colours contain a fake header
+        bytes.extend_from_slice(&[
+            b'A', b'd', b'a', 0, 2, 0x57, b'A', b'd', b'a', 0, 0, 0, 0, 0, 0,
+        ]);
+        bytes.extend_from_slice(&SPEC_FRAME);
+        let frames = decode_all(&mut decoder, &bytes);
+        assert_eq!(
+            frames,
+            [
+                Err(CommandError::InvalidHeader),
+                Err(CommandError::BufferTooSmall),
+                Ok(SPEC_FRAME[6..].to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let pixels: Vec<u8> = (0..=255).collect::<Vec<u8>>()[..255].to_vec();
+        let mut buf = [0_u8; 300];
+        let len = Command::Stream(&pixels[..])
+            .try_write_adalight(&mut buf)
+            .unwrap();
+        let mut decoder = AdalightDecoder::new([0_u8; 255]);
+        assert_eq!(decode_all(&mut decoder, &buf[..len]), [Ok(pixels)]);
+    }
+}
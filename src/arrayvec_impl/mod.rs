@@ -0,0 +1,70 @@
+#![cfg(feature = "arrayvec_impl")]
+use crate::{Command, CommandError};
+use arrayvec::ArrayVec;
+use core::convert::TryFrom;
+
+/// Copies `Stream` payload into a fixed capacity buffer, fails with `BufferTooSmall` when it holds more than `N` bytes
+impl<const N: usize> TryFrom<Command<&[u8]>> for Command<ArrayVec<u8, N>> {
+    type Error = CommandError;
+    fn try_from(src: Command<&[u8]>) -> Result<Self, Self::Error> {
+        src.try_map(|bytes| ArrayVec::try_from(bytes).map_err(|_| CommandError::BufferTooSmall))
+    }
+}
+
+impl<const N: usize> TryFrom<&[u8]> for Command<ArrayVec<u8, N>> {
+    type Error = CommandError;
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Command::<&[u8]>::try_from(value).and_then(Command::try_from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Command, CommandError};
+    use arrayvec::ArrayVec;
+    use core::convert::TryFrom;
+
+    #[test]
+    fn keeps_stream_past_receive_buffer() {
+        let cmd = {
+            let received = [b's', 1, 2, 3, 4, 5, 6];
+            Command::<ArrayVec<u8, 6>>::try_from(&received[..]).unwrap()
+        };
+        assert_eq!(
+            cmd,
+            Command::Stream(ArrayVec::try_from(&[1, 2, 3, 4, 5, 6][..]).unwrap())
+        );
+    }
+
+    #[test]
+    fn given_small_capacity_fails() {
+        let outcome = Command::<ArrayVec<u8, 3>>::try_from(Command::Stream(&[0_u8; 6][..]));
+        assert_eq!(outcome, Err(CommandError::BufferTooSmall));
+        let outcome = Command::<ArrayVec<u8, 0>>::try_from(&[b'c', 0, 1, 2, 3, 4][..]);
+        assert_eq!(
+            outcome,
+            Ok(Command::Constant {
+                led_count: 1,
+                colour: (2, 3, 4),
+            })
+        );
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn serde_e2e() {
+        let cmd: Command<ArrayVec<u8, 6>> =
+            Command::Stream(ArrayVec::try_from(&[1, 2, 3, 4, 5, 6][..]).unwrap());
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Command<ArrayVec<u8, 6>>>(&json).unwrap(),
+            cmd
+        );
+        assert!(serde_json::from_str::<Command<ArrayVec<u8, 3>>>(&json).is_err());
+        let binary = bincode::serialize(&cmd).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Command<ArrayVec<u8, 6>>>(&binary).unwrap(),
+            cmd
+        );
+    }
+}
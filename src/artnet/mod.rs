@@ -0,0 +1,561 @@
+#![cfg(feature = "artnet_impl")]
+//! Art-Net node turning ArtDmx universes into `Stream` frames and answering ArtPoll.
+//!
+//! Universes of a [`Layout`] are Art-Net 15 bit port addresses, net in the high 7 bits,
+//! sub-net and universe in the low 8.
+extern crate std;
+use crate::{
+    dmx::{is_out_of_order, Assembler, Layout, UNIVERSE_LEN},
+    Command,
+};
+use core::{
+    fmt::{Display, Formatter, Result as FMTResult},
+    str,
+};
+use std::{
+    io,
+    net::{Ipv4Addr, UdpSocket},
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// UDP port Art-Net is sent to
+pub const PORT: u16 = 6454;
+
+/// Protocol revision sent in every packet
+pub const PROTOCOL_VERSION: u16 = 14;
+
+/// Highest 15 bit port address, packets carry no more
+pub const MAX_PORT_ADDRESS: u16 = 0x7fff;
+
+const ID: &[u8; 8] = b"Art-Net\0";
+const OP_POLL: u16 = 0x2000;
+const OP_POLL_REPLY: u16 = 0x2100;
+const OP_DMX: u16 = 0x5000;
+const HEADER_LEN: usize = 12;
+const DMX_HEADER_LEN: usize = 18;
+const POLL_LEN: usize = 14;
+const POLL_REPLY_LEN: usize = 239;
+const SHORT_NAME_LEN: usize = 18;
+const LONG_NAME_LEN: usize = 64;
+/// Ports described by one ArtPollReply
+const MAX_PORTS: usize = 4;
+/// Port can output DMX512 received over Art-Net
+const PORT_TYPE_OUTPUT: u8 = 0x80;
+/// Output is transmitting data
+const GOOD_OUTPUT_TRANSMITTING: u8 = 0x80;
+/// Node supports 15 bit port addresses
+const STATUS2_PORT_ADDRESS_15BIT: u8 = 0x08;
+/// OEM code for nodes without one
+const OEM_UNKNOWN: u16 = 0x00ff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtNetError {
+    /// Packet ends before its header or declared data does
+    Truncated,
+    /// Missing `Art-Net` identifier
+    NotArtNet,
+    /// Valid Art-Net packet this module does not handle, e.g. ArtSync
+    UnsupportedOpCode(u16),
+    /// Layout spans universes past [`MAX_PORT_ADDRESS`]
+    LayoutOutOfRange,
+}
+
+impl Display for ArtNetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        match self {
+            ArtNetError::Truncated => write!(f, "Art-Net packet is truncated"),
+            ArtNetError::NotArtNet => write!(f, "Not an Art-Net packet"),
+            ArtNetError::UnsupportedOpCode(op) => {
+                write!(f, "Unsupported Art-Net op code {:#06x}", op)
+            }
+            ArtNetError::LayoutOutOfRange => {
+                write!(
+                    f,
+                    "Layout spans universes past Art-Net port address {:#06x}",
+                    MAX_PORT_ADDRESS
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArtNetError {}
+
+/// DMX data of one universe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtDmx<'a> {
+    /// 1 to 255 to reorder packets, 0 disables reordering
+    pub sequence: u8,
+    /// Physical input port the data came from, informative only
+    pub physical: u8,
+    pub port_address: u16,
+    /// Channel values, up to 512
+    pub data: &'a [u8],
+}
+
+/// Request for nodes to announce themselves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArtPoll {
+    pub flags: u8,
+    pub diag_priority: u8,
+}
+
+/// Node description sent in answer to [`ArtPoll`], for up to 4 port addresses sharing net and sub-net
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtPollReply {
+    pub ip: Ipv4Addr,
+    /// Up to 17 bytes
+    pub short_name: String,
+    /// Up to 63 bytes
+    pub long_name: String,
+    pub mac: [u8; 6],
+    /// Orders replies of a node with more than 4 ports, starting at 1
+    pub bind_index: u8,
+    /// Output ports, only the low 4 bits of all but the first are sent
+    pub port_addresses: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet<'a> {
+    Dmx(ArtDmx<'a>),
+    Poll(ArtPoll),
+    PollReply(ArtPollReply),
+}
+
+fn header(op: u16, len: usize) -> Vec<u8> {
+    let mut packet = vec![0_u8; len];
+    packet[..8].copy_from_slice(ID);
+    packet[8..10].copy_from_slice(&op.to_le_bytes());
+    packet
+}
+
+fn versioned_header(op: u16, len: usize) -> Vec<u8> {
+    let mut packet = header(op, len);
+    packet[10..12].copy_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    packet
+}
+
+/// Text up to the first nul, cut before invalid UTF-8
+fn text(bytes: &[u8]) -> String {
+    let bytes = &bytes[..bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len())];
+    match str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(err) => str::from_utf8(&bytes[..err.valid_up_to()])
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// Copies `text` into `field` leaving room for the nul terminator
+fn put_text(field: &mut [u8], text: &str) {
+    let len = text.len().min(field.len() - 1);
+    field[..len].copy_from_slice(&text.as_bytes()[..len]);
+}
+
+impl<'a> Packet<'a> {
+    pub fn parse(packet: &'a [u8]) -> Result<Self, ArtNetError> {
+        if packet.len() < HEADER_LEN {
+            return Err(ArtNetError::Truncated);
+        }
+        if &packet[..8] != ID {
+            return Err(ArtNetError::NotArtNet);
+        }
+        match u16::from_le_bytes([packet[8], packet[9]]) {
+            OP_DMX => {
+                if packet.len() < DMX_HEADER_LEN {
+                    return Err(ArtNetError::Truncated);
+                }
+                let len = (u16::from_be_bytes([packet[16], packet[17]]) as usize).min(UNIVERSE_LEN);
+                let data = packet
+                    .get(DMX_HEADER_LEN..DMX_HEADER_LEN + len)
+                    .ok_or(ArtNetError::Truncated)?;
+                Ok(Packet::Dmx(ArtDmx {
+                    sequence: packet[12],
+                    physical: packet[13],
+                    port_address: u16::from_le_bytes([packet[14], packet[15] & 0x7f]),
+                    data,
+                }))
+            }
+            OP_POLL => {
+                // Older controllers send only the first two fields
+                if packet.len() < POLL_LEN {
+                    return Err(ArtNetError::Truncated);
+                }
+                Ok(Packet::Poll(ArtPoll {
+                    flags: packet[12],
+                    diag_priority: packet[13],
+                }))
+            }
+            OP_POLL_REPLY => {
+                // Fields up to the bind index, later ones were added in revisions of the protocol
+                if packet.len() < 212 {
+                    return Err(ArtNetError::Truncated);
+                }
+                let net = ((packet[18] & 0x7f) as u16) << 8 | ((packet[19] & 0x0f) as u16) << 4;
+                let ports =
+                    (u16::from_be_bytes([packet[172], packet[173]]) as usize).min(MAX_PORTS);
+                let mut mac = [0_u8; 6];
+                mac.copy_from_slice(&packet[201..207]);
+                Ok(Packet::PollReply(ArtPollReply {
+                    ip: Ipv4Addr::new(packet[10], packet[11], packet[12], packet[13]),
+                    short_name: text(&packet[26..26 + SHORT_NAME_LEN]),
+                    long_name: text(&packet[44..44 + LONG_NAME_LEN]),
+                    mac,
+                    bind_index: packet[211],
+                    port_addresses: packet[190..190 + ports]
+                        .iter()
+                        .map(|universe| net | (universe & 0x0f) as u16)
+                        .collect(),
+                }))
+            }
+            op => Err(ArtNetError::UnsupportedOpCode(op)),
+        }
+    }
+}
+
+impl ArtDmx<'_> {
+    /// Encodes the packet, data is cut to 512 channels and padded to an even length
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data = &self.data[..self.data.len().min(UNIVERSE_LEN)];
+        let len = (data.len() + data.len() % 2).max(2);
+        let mut packet = versioned_header(OP_DMX, DMX_HEADER_LEN + len);
+        packet[12] = self.sequence;
+        packet[13] = self.physical;
+        packet[14..16].copy_from_slice(&(self.port_address & 0x7fff).to_le_bytes());
+        packet[16..18].copy_from_slice(&(len as u16).to_be_bytes());
+        packet[DMX_HEADER_LEN..DMX_HEADER_LEN + data.len()].copy_from_slice(data);
+        packet
+    }
+}
+
+impl ArtPoll {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut packet = versioned_header(OP_POLL, POLL_LEN);
+        packet[12] = self.flags;
+        packet[13] = self.diag_priority;
+        packet
+    }
+}
+
+impl ArtPollReply {
+    /// Encodes the reply, ports past the 4th are left out
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut packet = header(OP_POLL_REPLY, POLL_REPLY_LEN);
+        let ports = &self.port_addresses[..self.port_addresses.len().min(MAX_PORTS)];
+        let first = ports.first().copied().unwrap_or_default();
+        packet[10..14].copy_from_slice(&self.ip.octets());
+        packet[14..16].copy_from_slice(&PORT.to_le_bytes());
+        packet[18] = (first >> 8) as u8 & 0x7f;
+        packet[19] = (first >> 4) as u8 & 0x0f;
+        packet[20..22].copy_from_slice(&OEM_UNKNOWN.to_be_bytes());
+        put_text(&mut packet[26..26 + SHORT_NAME_LEN], &self.short_name);
+        put_text(&mut packet[44..44 + LONG_NAME_LEN], &self.long_name);
+        put_text(
+            &mut packet[108..172],
+            "#0001 [0000] Power On Tests successful",
+        );
+        packet[172..174].copy_from_slice(&(ports.len() as u16).to_be_bytes());
+        for (index, port_address) in ports.iter().enumerate() {
+            packet[174 + index] = PORT_TYPE_OUTPUT;
+            packet[182 + index] = GOOD_OUTPUT_TRANSMITTING;
+            packet[190 + index] = (port_address & 0x0f) as u8;
+        }
+        packet[201..207].copy_from_slice(&self.mac);
+        packet[207..211].copy_from_slice(&self.ip.octets());
+        packet[211] = self.bind_index;
+        packet[212] = STATUS2_PORT_ADDRESS_15BIT;
+        packet
+    }
+}
+
+/// Art-Net output node driving a strip laid out over one or more universes
+#[derive(Debug, Clone)]
+pub struct Node {
+    assembler: Assembler,
+    ip: Ipv4Addr,
+    mac: [u8; 6],
+    short_name: String,
+    long_name: String,
+    sequences: Vec<u8>,
+}
+
+impl Node {
+    /// `ip` is announced in poll replies, controllers send ArtDmx to it.
+    /// Fails with `LayoutOutOfRange` when `layout` spans universes past [`MAX_PORT_ADDRESS`]
+    pub fn new(layout: Layout, ip: Ipv4Addr) -> Result<Self, ArtNetError> {
+        let last = layout.first_universe() as usize + layout.universe_count().max(1) - 1;
+        if last > MAX_PORT_ADDRESS as usize {
+            return Err(ArtNetError::LayoutOutOfRange);
+        }
+        Ok(Self {
+            assembler: Assembler::new(layout),
+            ip,
+            mac: [0; 6],
+            short_name: "LED strip".to_string(),
+            long_name: "LED strip command bridge".to_string(),
+            sequences: vec![0; layout.universe_count()],
+        })
+    }
+
+    /// Names shown by controllers, cut to 17 and 63 bytes
+    pub fn with_names(mut self, short_name: &str, long_name: &str) -> Self {
+        self.short_name = short_name.to_string();
+        self.long_name = long_name.to_string();
+        self
+    }
+
+    pub fn with_mac(mut self, mac: [u8; 6]) -> Self {
+        self.mac = mac;
+        self
+    }
+
+    pub fn layout(&self) -> &Layout {
+        self.assembler.layout()
+    }
+
+    /// Replies describing every universe of the layout,
+    /// one per run of up to 4 port addresses sharing net and sub-net
+    pub fn poll_replies(&self) -> Vec<ArtPollReply> {
+        let mut replies: Vec<ArtPollReply> = Vec::new();
+        for port_address in self.layout().universes() {
+            match replies.last_mut() {
+                Some(reply)
+                    if reply.port_addresses.len() < MAX_PORTS
+                        && reply.port_addresses[0] >> 4 == port_address >> 4 =>
+                {
+                    reply.port_addresses.push(port_address)
+                }
+                _ => replies.push(ArtPollReply {
+                    ip: self.ip,
+                    short_name: self.short_name.clone(),
+                    long_name: self.long_name.clone(),
+                    mac: self.mac,
+                    bind_index: (replies.len() + 1).min(u8::MAX as usize) as u8,
+                    port_addresses: vec![port_address],
+                }),
+            }
+        }
+        replies
+    }
+
+    /// Copies `dmx` into the frame, returns the frame it completes.
+    /// Universes outside the layout and packets arriving out of order are ignored
+    pub fn handle_dmx(&mut self, dmx: &ArtDmx<'_>) -> Option<Command<&[u8]>> {
+        let index = self.layout().index(dmx.port_address)?;
+        if dmx.sequence != 0 {
+            let last = self.sequences[index];
+            if last != 0 && is_out_of_order(last, dmx.sequence) {
+                return None;
+            }
+            self.sequences[index] = dmx.sequence;
+        }
+        if self.assembler.update(dmx.port_address, dmx.data) {
+            Some(self.assembler.frame())
+        } else {
+            None
+        }
+    }
+
+    /// Receives packets from `socket` until one completes a frame, answering polls on the way.
+    /// Replies go straight to the controller that polled, invalid packets are skipped
+    pub fn recv(&mut self, socket: &UdpSocket) -> io::Result<Command<&[u8]>> {
+        let mut buf = [0_u8; DMX_HEADER_LEN + UNIVERSE_LEN];
+        loop {
+            let (len, from) = socket.recv_from(&mut buf)?;
+            match Packet::parse(&buf[..len]) {
+                Ok(Packet::Dmx(dmx)) if self.handle_dmx(&dmx).is_some() => {
+                    return Ok(self.assembler.frame());
+                }
+                Ok(Packet::Poll(_)) => {
+                    for reply in self.poll_replies() {
+                        socket.send_to(&reply.to_bytes(), from)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArtDmx, ArtNetError, ArtPoll, ArtPollReply, Node, Packet};
+    use crate::{dmx::Layout, Command};
+    extern crate std;
+    use std::{
+        net::{Ipv4Addr, UdpSocket},
+        vec,
+        vec::Vec,
+    };
+
+    /// Synthetic ArtDmx laid out by hand from the Art-Net 4 spec, not a capture.
+    /// Universe 0x0123, sequence 7, 4 channels where a desk would send 512
+    const SPEC_DMX: [u8; 22] = [
+        b'A', b'r', b't', b'-', b'N', b'e', b't', 0, 0x00, 0x50, 0, 14, 7, 0, 0x23, 0x01, 0, 4,
+        255, 128, 0, 9,
+    ];
+
+    /// Synthetic ArtPoll laid out by hand from the Art-Net 4 spec, not a capture
+    const SPEC_POLL: [u8; 14] = [
+        b'A', b'r', b't', b'-', b'N', b'e', b't', 0, 0x00, 0x20, 0, 14, 0x06, 0x10,
+    ];
+
+    fn dmx(port_address: u16, sequence: u8, data: &[u8]) -> Vec<u8> {
+        ArtDmx {
+            sequence,
+            physical: 0,
+            port_address,
+            data,
+        }
+        .to_bytes()
+    }
+
+    #[test]
+    fn parses_spec_packets() {
+        let packet = Packet::parse(&SPEC_DMX).unwrap();
+        let expected = ArtDmx {
+            sequence: 7,
+            physical: 0,
+            port_address: 0x0123,
+            data: &[255, 128, 0, 9],
+        };
+        assert_eq!(packet, Packet::Dmx(expected));
+        assert_eq!(expected.to_bytes(), SPEC_DMX);
+        let poll = ArtPoll {
+            flags: 0x06,
+            diag_priority: 0x10,
+        };
+        assert_eq!(Packet::parse(&SPEC_POLL), Ok(Packet::Poll(poll)));
+        assert_eq!(poll.to_bytes(), SPEC_POLL);
+    }
+
+    #[test]
+    fn pads_odd_dmx_length() {
+        let packet = dmx(1, 0, &[1, 2, 3]);
+        assert_eq!(&packet[16..], &[0, 4, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn given_broken_packet_returns_error() {
+        assert_eq!(Packet::parse(&SPEC_DMX[..10]), Err(ArtNetError::Truncated));
+        assert_eq!(Packet::parse(&SPEC_DMX[..21]), Err(ArtNetError::Truncated));
+        let mut other = SPEC_DMX;
+        other[0] = b'X';
+        assert_eq!(Packet::parse(&other), Err(ArtNetError::NotArtNet));
+        let mut sync = SPEC_POLL;
+        sync[9] = 0x52;
+        assert_eq!(
+            Packet::parse(&sync),
+            Err(ArtNetError::UnsupportedOpCode(0x5200))
+        );
+    }
+
+    #[test]
+    fn given_layout_past_port_addresses_fails() {
+        let fits = Layout::new(0x7ffe, 340);
+        assert!(Node::new(fits, Ipv4Addr::LOCALHOST).is_ok());
+        let past = Layout::new(0x7ffe, 341);
+        assert_eq!(
+            Node::new(past, Ipv4Addr::LOCALHOST).err(),
+            Some(ArtNetError::LayoutOutOfRange)
+        );
+        let empty = Layout::new(0x8000, 0);
+        assert!(Node::new(empty, Ipv4Addr::LOCALHOST).is_err());
+    }
+
+    #[test]
+    fn replies_per_sub_net() {
+        let node = Node::new(
+            Layout::new(0x0e, 6).with_leds_per_universe(1),
+            Ipv4Addr::LOCALHOST,
+        )
+        .unwrap();
+        let replies = node.poll_replies();
+        let ports: Vec<_> = replies
+            .iter()
+            .map(|reply| reply.port_addresses.clone())
+            .collect();
+        assert_eq!(ports, vec![vec![0x0e, 0x0f], vec![0x10, 0x11, 0x12, 0x13]]);
+        assert_eq!(replies[1].bind_index, 2);
+        let bytes = replies[1].to_bytes();
+        assert_eq!(bytes.len(), 239);
+        assert_eq!((bytes[18], bytes[19]), (0, 1));
+        assert_eq!(
+            Packet::parse(&bytes),
+            Ok(Packet::PollReply(replies[1].clone()))
+        );
+    }
+
+    #[test]
+    fn cuts_long_names() {
+        let reply = ArtPollReply {
+            ip: Ipv4Addr::new(10, 0, 0, 2),
+            short_name: "a very long short name".into(),
+            long_name: "long".into(),
+            mac: [1, 2, 3, 4, 5, 6],
+            bind_index: 1,
+            port_addresses: vec![0x7fff],
+        };
+        match Packet::parse(&reply.to_bytes()).unwrap() {
+            Packet::PollReply(parsed) => {
+                assert_eq!(parsed.short_name, "a very long short");
+                assert_eq!(parsed.port_addresses, [0x7fff]);
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn assembles_universes_in_order() {
+        let mut node = Node::new(
+            Layout::new(1, 3).with_leds_per_universe(2),
+            Ipv4Addr::LOCALHOST,
+        )
+        .unwrap();
+        let first = dmx(1, 10, &[1, 1, 1, 2, 2, 2]);
+        let Packet::Dmx(first) = Packet::parse(&first).unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(node.handle_dmx(&first), None);
+        // Out of order, replays the old value
+        assert_eq!(node.handle_dmx(&first), None);
+        let second = dmx(2, 10, &[3; 3]);
+        let Packet::Dmx(second) = Packet::parse(&second).unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(
+            node.handle_dmx(&second),
+            Some(Command::Stream(&[1, 1, 1, 2, 2, 2, 3, 3, 3][..]))
+        );
+    }
+
+    #[test]
+    fn e2e_loopback() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let controller = UdpSocket::bind("127.0.0.1:0").unwrap();
+        controller.connect(socket.local_addr().unwrap()).unwrap();
+        let mut node = Node::new(Layout::new(0, 200), Ipv4Addr::LOCALHOST)
+            .unwrap()
+            .with_names("strip", "Test strip");
+
+        controller.send(&SPEC_POLL).unwrap();
+        controller.send(b"garbage").unwrap();
+        controller.send(&dmx(0, 0, &[5; 510])).unwrap();
+        controller.send(&dmx(1, 0, &[6; 90])).unwrap();
+        let frame = node.recv(&socket).unwrap();
+        assert_eq!(frame.pixel(169), Some((5, 5, 5)));
+        assert_eq!(frame.pixel(199), Some((6, 6, 6)));
+
+        let mut buf = [0_u8; 512];
+        let len = controller.recv(&mut buf).unwrap();
+        match Packet::parse(&buf[..len]).unwrap() {
+            Packet::PollReply(reply) => {
+                assert_eq!(reply.short_name, "strip");
+                assert_eq!(reply.port_addresses, [0, 1]);
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Encodes, decodes and sends commands.
+//!
+//! ```text
+//! echo '{"type":"constant","led_count":60,"colour":"red"}' | command-cli convert --to hex
+//! command-cli show --from hex frames.txt
+//! command-cli send --from json tcp://192.168.1.20:9000 scene.json
+//! ```
+use clap::{Parser, Subcommand};
+use command::cli::{read_commands, CliError, Format, Sink, Target};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "command-cli",
+    version,
+    about = "Encodes, decodes and sends LED strip commands"
+)]
+struct Args {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand)]
+enum Action {
+    /// Converts commands between json, hex and native (length prefixed) formats
+    Convert {
+        #[arg(long, default_value = "json")]
+        from: Format,
+        /// json, hex, native or text
+        #[arg(long, default_value = "hex")]
+        to: Format,
+        /// Input file, standard input when omitted
+        input: Option<PathBuf>,
+    },
+    /// Pretty prints decoded commands
+    Show {
+        #[arg(long, default_value = "native")]
+        from: Format,
+        input: Option<PathBuf>,
+    },
+    /// Sends commands to `-` (stdout), `tcp://host:port`, `udp://host:port` or a serial device path
+    Send {
+        #[arg(long, default_value = "json")]
+        from: Format,
+        /// Format used when the target is stdout
+        #[arg(long, default_value = "native")]
+        to: Format,
+        target: Target,
+        input: Option<PathBuf>,
+    },
+}
+
+fn open(input: Option<PathBuf>) -> io::Result<Box<dyn BufRead>> {
+    Ok(match input {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    })
+}
+
+fn run(action: Action) -> Result<(), CliError> {
+    let (from, input, target, to) = match action {
+        Action::Convert { from, to, input } => (from, input, Target::Stdout, to),
+        Action::Show { from, input } => (from, input, Target::Stdout, Format::Text),
+        Action::Send {
+            from,
+            to,
+            target,
+            input,
+        } => (from, input, target, to),
+    };
+    let mut sink = Sink::open(&target, to)?;
+    for command in read_commands(from, open(input)?)? {
+        sink.send(&command?)?;
+    }
+    sink.flush()
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse().action) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("command-cli: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
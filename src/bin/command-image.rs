@@ -0,0 +1,117 @@
+//! Turns images and GIF animations into `Stream` commands.
+//!
+//! ```text
+//! command-image --leds 60 --gamma 2.2 --record sheet.ledr sheet.png
+//! command-image --points layout.json --fps 25 fire.gif udp://192.168.1.20:9000
+//! ```
+use clap::Parser;
+use command::{
+    cli::{CliError, Format, Sink, Target},
+    image_impl::{convert_file, ImageOptions, Mapping, MAX_FPS},
+    recording::RecordingWriter,
+};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::PathBuf,
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Parser)]
+#[command(
+    name = "command-image",
+    version,
+    about = "Converts images and GIF animations into LED strip commands"
+)]
+struct Args {
+    /// Scales images to this many pixels wide before mapping them
+    #[arg(long)]
+    leds: Option<u32>,
+    /// Samples every frame at the `[[x, y], ...]` points in this JSON file, one per LED,
+    /// instead of playing image rows as frames
+    #[arg(long)]
+    points: Option<PathBuf>,
+    /// Gamma correction exponent, 1 keeps colours as they are
+    #[arg(long, default_value_t = 1.0, value_parser = parse_positive)]
+    gamma: f32,
+    /// Output frame rate, sprite sheets default to 30, GIFs keep their own timing when omitted
+    #[arg(long, value_parser = parse_fps)]
+    fps: Option<f32>,
+    /// Writes a recording for `command-replay` instead of sending
+    #[arg(long, conflicts_with = "target")]
+    record: Option<PathBuf>,
+    /// Format used when the target is stdout
+    #[arg(long, default_value = "text")]
+    to: Format,
+    /// PNG, GIF or any other image `image` decodes
+    image: PathBuf,
+    /// `-` (stdout), `tcp://host:port`, `udp://host:port` or a serial device path
+    #[arg(default_value = "-")]
+    target: Target,
+}
+
+fn parse_positive(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(number) if number.is_finite() && number > 0.0 => Ok(number),
+        Ok(_) => Err("must be a finite number above 0".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn parse_fps(value: &str) -> Result<f32, String> {
+    match parse_positive(value)? {
+        fps if fps <= MAX_FPS => Ok(fps),
+        _ => Err(format!("must be at most {}", MAX_FPS)),
+    }
+}
+
+fn mapping(points: Option<PathBuf>) -> Result<Mapping, CliError> {
+    match points {
+        Some(path) => Ok(Mapping::Points(serde_json::from_reader(BufReader::new(
+            File::open(path)?,
+        ))?)),
+        None => Ok(Mapping::Rows),
+    }
+}
+
+fn run(args: Args) -> Result<(), CliError> {
+    let options = ImageOptions {
+        mapping: mapping(args.points)?,
+        width: args.leds,
+        gamma: args.gamma,
+        fps: args.fps,
+    };
+    let frames = convert_file(&args.image, &options)
+        .map_err(|err| CliError::Io(io::Error::new(io::ErrorKind::InvalidData, err)))?;
+
+    if let Some(output) = args.record {
+        let mut writer = RecordingWriter::new(BufWriter::new(File::create(output)?))?;
+        for (at, command) in frames.iter() {
+            writer.record(*at, command)?;
+        }
+        writer.flush()?;
+        return Ok(());
+    }
+
+    let mut sink = Sink::open(&args.target, args.to)?;
+    let started = Instant::now();
+    for (at, command) in frames.iter() {
+        let due = Duration::from_millis(*at as u64);
+        thread::sleep(due.saturating_sub(started.elapsed()));
+        sink.send(command)?;
+        sink.flush()?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("command-image: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
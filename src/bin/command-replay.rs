@@ -0,0 +1,168 @@
+//! Records command sessions to disk and replays them.
+//!
+//! ```text
+//! command-replay record --udp 0.0.0.0:9000 session.ledr
+//! command-replay replay --speed 2 session.ledr udp://192.168.1.20:9000
+//! command-replay print session.ledr
+//! ```
+use clap::{Parser, Subcommand};
+use command::{
+    cli::{read_commands, CliError, Format, Sink, Target},
+    recording::{RecordingReader, RecordingWriter},
+    Command,
+};
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    net::UdpSocket,
+    path::PathBuf,
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Parser)]
+#[command(
+    name = "command-replay",
+    version,
+    about = "Records and replays LED strip command sessions"
+)]
+struct Args {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand)]
+enum Action {
+    /// Records commands as they arrive, until the input ends or the process is interrupted
+    Record {
+        /// Format of commands read from INPUT
+        #[arg(long, default_value = "json")]
+        from: Format,
+        /// Receives natively encoded commands, one per datagram, instead of reading INPUT
+        #[arg(long, conflicts_with = "input")]
+        udp: Option<String>,
+        output: PathBuf,
+        /// Input file or pipe, standard input when omitted
+        input: Option<PathBuf>,
+    },
+    /// Sends recorded commands with their original timing
+    Replay {
+        /// Playback speed, 2 plays twice as fast
+        #[arg(long, default_value_t = 1.0, value_parser = parse_speed)]
+        speed: f64,
+        /// Format used when the target is stdout
+        #[arg(long, default_value = "text")]
+        to: Format,
+        recording: PathBuf,
+        /// `-` (stdout), `tcp://host:port`, `udp://host:port` or a serial device path
+        #[arg(default_value = "-")]
+        target: Target,
+    },
+    /// Prints recorded commands with their timestamps, without waiting
+    Print { recording: PathBuf },
+}
+
+fn parse_speed(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        Ok(_) => Err("speed must be a finite number above 0".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn millis(started: Instant) -> u32 {
+    started.elapsed().as_millis().min(u32::MAX as u128) as u32
+}
+
+fn record(
+    from: Format,
+    udp: Option<String>,
+    output: PathBuf,
+    input: Option<PathBuf>,
+) -> Result<(), CliError> {
+    let mut writer = RecordingWriter::new(BufWriter::new(File::create(output)?))?;
+    let started = Instant::now();
+    match udp {
+        Some(addr) => {
+            let socket = UdpSocket::bind(addr)?;
+            let mut buf = [0_u8; u16::MAX as usize];
+            loop {
+                let len = socket.recv(&mut buf)?;
+                let at = millis(started);
+                match Command::<&[u8]>::try_from(&buf[..len]) {
+                    Ok(command) => {
+                        writer.record(at, &command)?;
+                        // Interrupting the recording should not lose what came before
+                        writer.flush()?;
+                    }
+                    Err(err) => eprintln!("command-replay: dropped datagram: {}", err),
+                }
+            }
+        }
+        None => {
+            let reader: Box<dyn BufRead> = match input {
+                Some(path) => Box::new(BufReader::new(File::open(path)?)),
+                None => Box::new(BufReader::new(io::stdin())),
+            };
+            for command in read_commands(from, reader)? {
+                let command = command?;
+                writer.record(millis(started), &command)?;
+                writer.flush()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn replay(speed: f64, to: Format, recording: PathBuf, target: Target) -> Result<(), CliError> {
+    let reader = RecordingReader::new(BufReader::new(File::open(recording)?))?;
+    let mut sink = Sink::open(&target, to)?;
+    let started = Instant::now();
+    for record in reader {
+        let (at, command) = record?;
+        // Tiny speeds push timestamps past what `Duration` holds
+        let due = Duration::try_from_secs_f64(at as f64 / 1000.0 / speed)
+            .map_err(|err| CliError::Io(io::Error::new(io::ErrorKind::InvalidInput, err)))?;
+        thread::sleep(due.saturating_sub(started.elapsed()));
+        sink.send(&command)?;
+        sink.flush()?;
+    }
+    Ok(())
+}
+
+fn print(recording: PathBuf) -> Result<(), CliError> {
+    let reader = RecordingReader::new(BufReader::new(File::open(recording)?))?;
+    let mut stdout = io::stdout();
+    for record in reader {
+        let (at, command) = record?;
+        write!(stdout, "{:>8} ms  {}", at, command)?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let outcome = match Args::parse().action {
+        Action::Record {
+            from,
+            udp,
+            output,
+            input,
+        } => record(from, udp, output, input),
+        Action::Replay {
+            speed,
+            to,
+            recording,
+            target,
+        } => replay(speed, to, recording, target),
+        Action::Print { recording } => print(recording),
+    };
+    match outcome {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("command-replay: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
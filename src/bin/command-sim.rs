@@ -0,0 +1,138 @@
+//! Previews commands as truecolor ANSI blocks in the terminal.
+//!
+//! ```text
+//! echo '{"type":"pulse","led_count":30,"start":"black","end":"teal","frames":50,"period":2000}' | command-sim --leds 30
+//! command-sim --udp 0.0.0.0:9000 --leds 144
+//! ```
+use clap::Parser;
+use command::{
+    cli::{read_commands, CliError, Format},
+    Command, Strip,
+};
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    net::UdpSocket,
+    path::PathBuf,
+    process::ExitCode,
+    sync::mpsc::{channel, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Parser)]
+#[command(
+    name = "command-sim",
+    version,
+    about = "Renders LED strip commands in the terminal"
+)]
+struct Args {
+    /// Number of LEDs on the simulated strip
+    #[arg(long, default_value_t = 60)]
+    leds: usize,
+    /// Format of commands read from INPUT
+    #[arg(long, default_value = "json")]
+    from: Format,
+    /// Receives natively encoded commands, one per datagram, instead of reading INPUT
+    #[arg(long, conflicts_with = "input")]
+    udp: Option<String>,
+    /// Input file or pipe, standard input when omitted
+    input: Option<PathBuf>,
+}
+
+type Received = Result<Command<Vec<u8>>, CliError>;
+
+fn read_input(
+    from: Format,
+    input: Option<PathBuf>,
+    sender: Sender<Received>,
+) -> Result<(), CliError> {
+    let reader: Box<dyn BufRead> = match input {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+    for command in read_commands(from, reader)? {
+        if sender.send(command).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn receive_udp(addr: String, sender: Sender<Received>) -> Result<(), CliError> {
+    let socket = UdpSocket::bind(addr)?;
+    let mut buf = [0_u8; u16::MAX as usize];
+    loop {
+        let len = socket.recv(&mut buf)?;
+        match Command::<&[u8]>::try_from(&buf[..len]) {
+            Ok(command) => {
+                if sender.send(Ok(command.into())).is_err() {
+                    return Ok(());
+                }
+            }
+            // One garbled datagram should not stop the preview
+            Err(err) => eprintln!("command-sim: dropped datagram: {}", err),
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), CliError> {
+    let (sender, receiver) = channel();
+    let input_sender = sender.clone();
+    let (from, input, udp) = (args.from, args.input, args.udp);
+    thread::spawn(move || {
+        let outcome = match udp {
+            Some(addr) => receive_udp(addr, input_sender.clone()),
+            None => read_input(from, input, input_sender.clone()),
+        };
+        if let Err(err) = outcome {
+            let _ = input_sender.send(Err(err));
+        }
+    });
+    drop(sender);
+
+    let started = Instant::now();
+    let now = || started.elapsed().as_millis() as u32;
+    let until = |at: u32| Duration::from_millis(at.saturating_sub(now()) as u64);
+    let mut strip = Strip::new(vec![0_u8; args.leds * 3]);
+    let mut stdout = io::stdout();
+    let mut open = true;
+    loop {
+        write!(stdout, "\r{}", strip.ansi())?;
+        stdout.flush()?;
+
+        let next = strip.next_frame_at(now());
+        if !open {
+            match next {
+                Some(at) => thread::sleep(until(at)),
+                None => break,
+            }
+            strip.render(now());
+            continue;
+        }
+        let received = match next {
+            Some(at) => receiver.recv_timeout(until(at)),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(command) => strip.apply(&command?, now()),
+            Err(RecvTimeoutError::Timeout) => {
+                strip.render(now());
+            }
+            Err(RecvTimeoutError::Disconnected) => open = false,
+        }
+    }
+    writeln!(stdout)?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("\ncommand-sim: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
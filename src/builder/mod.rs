@@ -0,0 +1,287 @@
+use crate::{Command, CommandError};
+use core::{
+    convert::TryFrom,
+    fmt::{Debug, Formatter, Result as FMTResult},
+    marker::PhantomData,
+};
+
+fn require<V>(value: Option<V>, field: &'static str) -> Result<V, CommandError> {
+    value.ok_or(CommandError::MissingField(field))
+}
+
+fn try_narrow<N: TryFrom<V>, V>(value: Option<V>, field: &'static str) -> Result<N, CommandError> {
+    N::try_from(require(value, field)?).map_err(|_| CommandError::ValueOutOfRange)
+}
+
+/// Builder for `Command::Constant`, see [`Command::constant`].
+/// Carries the storage type, so `build` needs no annotation where the command is used.
+pub struct ConstantBuilder<T> {
+    leds: Option<usize>,
+    colour: Option<(u8, u8, u8)>,
+    storage: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for ConstantBuilder<T> {
+    fn default() -> Self {
+        Self {
+            leds: None,
+            colour: None,
+            storage: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for ConstantBuilder<T> {
+    fn clone(&self) -> Self {
+        Self {
+            leds: self.leds,
+            colour: self.colour,
+            storage: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for ConstantBuilder<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        f.debug_struct("ConstantBuilder")
+            .field("leds", &self.leds)
+            .field("colour", &self.colour)
+            .finish()
+    }
+}
+
+impl<T> ConstantBuilder<T> {
+    pub fn leds(mut self, leds: usize) -> Self {
+        self.leds = Some(leds);
+        self
+    }
+
+    pub fn colour(mut self, colour: (u8, u8, u8)) -> Self {
+        self.colour = Some(colour);
+        self
+    }
+
+    /// Fails with `ValueOutOfRange` when LED count overflows `u16`
+    /// and with `MissingField` naming the first field that was not set
+    pub fn build(self) -> Result<Command<T>, CommandError> {
+        Ok(Command::Constant {
+            led_count: try_narrow(self.leds, "leds")?,
+            colour: require(self.colour, "colour")?,
+        })
+    }
+}
+
+/// Builder for `Command::Pulse`, see [`Command::pulse`]
+pub struct PulseBuilder<T> {
+    leds: Option<usize>,
+    start: Option<(u8, u8, u8)>,
+    end: Option<(u8, u8, u8)>,
+    frames: Option<u32>,
+    period_ms: Option<u32>,
+    storage: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for PulseBuilder<T> {
+    fn default() -> Self {
+        Self {
+            leds: None,
+            start: None,
+            end: None,
+            frames: None,
+            period_ms: None,
+            storage: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for PulseBuilder<T> {
+    fn clone(&self) -> Self {
+        Self {
+            leds: self.leds,
+            start: self.start,
+            end: self.end,
+            frames: self.frames,
+            period_ms: self.period_ms,
+            storage: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for PulseBuilder<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        f.debug_struct("PulseBuilder")
+            .field("leds", &self.leds)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("frames", &self.frames)
+            .field("period_ms", &self.period_ms)
+            .finish()
+    }
+}
+
+impl<T> PulseBuilder<T> {
+    pub fn leds(mut self, leds: usize) -> Self {
+        self.leds = Some(leds);
+        self
+    }
+
+    /// Colour the pulse starts with
+    pub fn from(mut self, start: (u8, u8, u8)) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Colour the pulse ends with
+    pub fn to(mut self, end: (u8, u8, u8)) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    pub fn frames(mut self, frames: u32) -> Self {
+        self.frames = Some(frames);
+        self
+    }
+
+    pub fn period_ms(mut self, period_ms: u32) -> Self {
+        self.period_ms = Some(period_ms);
+        self
+    }
+
+    /// Fails with `ValueOutOfRange` when LED count or period overflow `u16` or frames overflow `u8`,
+    /// and with `MissingField` naming the first field that was not set
+    pub fn build(self) -> Result<Command<T>, CommandError> {
+        Ok(Command::Pulse {
+            led_count: try_narrow(self.leds, "leds")?,
+            start: require(self.start, "from")?,
+            end: require(self.end, "to")?,
+            frames: try_narrow(self.frames, "frames")?,
+            period: try_narrow(self.period_ms, "period_ms")?,
+        })
+    }
+}
+
+impl<T> Command<T> {
+    pub fn constant() -> ConstantBuilder<T> {
+        ConstantBuilder::default()
+    }
+
+    pub fn pulse() -> PulseBuilder<T> {
+        PulseBuilder::default()
+    }
+}
+
+impl<'a> Command<&'a [u8]> {
+    /// Writes colours into `buf` and returns `Stream` over the written part.
+    /// Fails with `BufferTooSmall` when `buf` cannot hold every pixel
+    pub fn stream_from_pixels<I>(pixels: I, buf: &'a mut [u8]) -> Result<Self, CommandError>
+    where
+        I: IntoIterator<Item = (u8, u8, u8)>,
+    {
+        let mut len = 0;
+        for (r, g, b) in pixels {
+            let rgb = buf
+                .get_mut(len..len + 3)
+                .ok_or(CommandError::BufferTooSmall)?;
+            rgb.copy_from_slice(&[r, g, b]);
+            len += 3;
+        }
+        Ok(Command::Stream(&buf[..len]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Command, CommandError};
+
+    #[test]
+    fn builds_pulse() {
+        let cmd: Command<&[u8]> = Command::pulse()
+            .leds(300)
+            .from((0, 0, 0))
+            .to((255, 0, 0))
+            .frames(60)
+            .period_ms(1000)
+            .build()
+            .expect("Valid pulse");
+        assert_eq!(
+            cmd,
+            Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            }
+        );
+    }
+
+    #[test]
+    fn given_overflow_pulse_fails() {
+        let pulse = Command::<&[u8]>::pulse()
+            .leds(300)
+            .from((0, 0, 0))
+            .to((255, 0, 0))
+            .frames(60)
+            .period_ms(1000);
+        let outcome = pulse.clone().leds(70_000).build();
+        assert_eq!(outcome.unwrap_err(), CommandError::ValueOutOfRange);
+        let outcome = pulse.clone().frames(256).build();
+        assert_eq!(outcome.unwrap_err(), CommandError::ValueOutOfRange);
+        let outcome = pulse.period_ms(65_536).build();
+        assert_eq!(outcome.unwrap_err(), CommandError::ValueOutOfRange);
+    }
+
+    #[test]
+    fn given_incomplete_builder_fails() {
+        let outcome = Command::<&[u8]>::pulse().leds(1).build();
+        assert_eq!(outcome.unwrap_err(), CommandError::MissingField("from"));
+        let outcome = Command::<&[u8]>::pulse()
+            .leds(1)
+            .from((0, 0, 0))
+            .to((1, 1, 1))
+            .frames(2)
+            .build();
+        assert_eq!(
+            outcome.unwrap_err(),
+            CommandError::MissingField("period_ms")
+        );
+        let outcome = Command::<&[u8]>::constant().colour((1, 2, 3)).build();
+        assert_eq!(outcome.unwrap_err(), CommandError::MissingField("leds"));
+    }
+
+    #[test]
+    fn builds_constant() {
+        fn send(cmd: Command<&[u8]>) -> Command<&[u8]> {
+            cmd
+        }
+        // Storage type comes from where the command goes, no annotation needed
+        let cmd = Command::constant()
+            .leds(60)
+            .colour((255, 0, 0))
+            .build()
+            .map(send);
+        assert_eq!(
+            cmd,
+            Ok(Command::Constant {
+                led_count: 60,
+                colour: (255, 0, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn builds_stream_from_pixels() {
+        let mut buf = [0_u8; 12];
+        let pixels = (0..3_u8).map(|idx| (idx, idx, idx));
+        let cmd = Command::stream_from_pixels(pixels, &mut buf).expect("Fits into buf");
+        assert_eq!(cmd, Command::Stream(&[0, 0, 0, 1, 1, 1, 2, 2, 2][..]));
+    }
+
+    #[test]
+    fn given_small_buf_stream_from_pixels_fails() {
+        let mut buf = [0_u8; 5];
+        let pixels = (0..2_u8).map(|idx| (idx, idx, idx));
+        let outcome = Command::stream_from_pixels(pixels, &mut buf);
+        assert_eq!(outcome.unwrap_err(), CommandError::BufferTooSmall);
+    }
+}
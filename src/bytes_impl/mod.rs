@@ -0,0 +1,86 @@
+#![cfg(feature = "bytes_impl")]
+//! `Command<Bytes>`, a `Stream` payload that is shared rather than copied when the command is cloned.
+extern crate alloc;
+use crate::{Command, CommandError};
+use alloc::vec::Vec;
+use bytes::Bytes;
+use core::convert::TryFrom;
+
+/// Decodes native encoding, `Stream` payload is a slice of `value` sharing its memory
+impl TryFrom<Bytes> for Command<Bytes> {
+    type Error = CommandError;
+    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
+        let command = Command::<&[u8]>::try_from(&value[..])?;
+        Ok(command.map(|payload| value.slice_ref(payload)))
+    }
+}
+
+impl<'a> From<Command<&'a [u8]>> for Command<Bytes> {
+    fn from(src: Command<&'a [u8]>) -> Self {
+        src.map(Bytes::copy_from_slice)
+    }
+}
+
+/// Takes over the allocation of the payload without copying it
+impl From<Command<Vec<u8>>> for Command<Bytes> {
+    fn from(src: Command<Vec<u8>>) -> Self {
+        src.map(Bytes::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Command;
+    use bytes::Bytes;
+    use core::convert::TryFrom;
+    extern crate alloc;
+    use alloc::vec;
+
+    #[test]
+    fn decodes_by_slicing() {
+        let frame = Bytes::from(vec![b's', 1, 2, 3]);
+        let cmd = Command::try_from(frame.clone()).unwrap();
+        match &cmd {
+            Command::Stream(payload) => {
+                assert_eq!(payload.as_ptr(), frame[1..].as_ptr());
+                assert_eq!(payload.as_ref(), &[1, 2, 3]);
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+        // Clones point at the same memory
+        let clone = cmd.clone();
+        assert!(
+            matches!(clone, Command::Stream(payload) if payload.as_ptr() == frame[1..].as_ptr())
+        );
+    }
+
+    #[test]
+    fn given_garbled_frame_fails() {
+        let outcome = Command::<Bytes>::try_from(Bytes::from_static(&[b'c', 0]));
+        assert_eq!(outcome, Err(crate::CommandError::MalformedPayload));
+    }
+
+    #[test]
+    fn converts_to_and_from_borrowed() {
+        let payload = [1_u8, 2, 3];
+        let cmd: Command<Bytes> = Command::Stream(&payload[..]).into();
+        assert_eq!(cmd.as_ref(), Command::Stream(&payload[..]));
+        let owned = vec![1_u8, 2, 3];
+        let ptr = owned.as_ptr();
+        let cmd: Command<Bytes> = Command::Stream(owned).into();
+        assert!(matches!(&cmd, Command::Stream(payload) if payload.as_ptr() == ptr));
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn serde_e2e() {
+        let cmd = Command::Stream(Bytes::from_static(&[1, 2, 3]));
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serde_json::from_str::<Command<Bytes>>(&json).unwrap(), cmd);
+        let binary = bincode::serialize(&cmd).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Command<Bytes>>(&binary).unwrap(),
+            cmd
+        );
+    }
+}
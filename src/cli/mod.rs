@@ -0,0 +1,453 @@
+#![cfg(feature = "cli")]
+//! Conversions and transports behind the `command-cli` binary.
+//!
+//! Byte streams (serial devices, TCP, stdout in `native` format) carry commands framed like `CommandCodec`,
+//! UDP sends one native encoded command per datagram.
+extern crate std;
+use crate::{
+    framing::{frame_len, frame_prefix, FRAME_PREFIX_LEN},
+    serde_impl::payload::Hex,
+    Command, CommandError,
+};
+use core::{
+    convert::TryFrom,
+    fmt::{Display, Formatter, Result as FMTResult},
+    str::FromStr,
+};
+use std::{
+    boxed::Box,
+    fs::OpenOptions,
+    io::{self, BufRead, Read, Write},
+    net::{TcpStream, UdpSocket},
+    path::PathBuf,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[derive(Debug)]
+pub enum CliError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// Line that is not an even number of hex digits
+    Hex(String),
+    Command(CommandError),
+    /// Format that cannot be used in this direction, e.g. reading `text`
+    Unsupported(Format),
+}
+
+impl From<io::Error> for CliError {
+    fn from(value: io::Error) -> Self {
+        CliError::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(value: serde_json::Error) -> Self {
+        CliError::Json(value)
+    }
+}
+
+impl From<CommandError> for CliError {
+    fn from(value: CommandError) -> Self {
+        CliError::Command(value)
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        match self {
+            CliError::Io(err) => write!(f, "IO error: {}", err),
+            CliError::Json(err) => write!(f, "JSON error: {}", err),
+            CliError::Hex(line) => write!(f, "Invalid hex: {:?}", line),
+            CliError::Command(err) => write!(f, "Command error: {}", err),
+            CliError::Unsupported(format) => write!(f, "Format {} is not supported here", format),
+        }
+    }
+}
+
+impl std::error::Error for CliError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CliError::Io(err) => Some(err),
+            CliError::Json(err) => Some(err),
+            CliError::Command(err) => Some(err),
+            CliError::Hex(_) | CliError::Unsupported(_) => None,
+        }
+    }
+}
+
+/// How commands are written on input and output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// JSON values as accepted by `serde_impl`, written one per line
+    Json,
+    /// Native encoding in hex, one command per line
+    Hex,
+    /// Native encoding with a big endian u16 length prefix, as `CommandCodec` frames it
+    Native,
+    /// `Display` output, can only be written
+    Text,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "hex" => Ok(Format::Hex),
+            "native" => Ok(Format::Native),
+            "text" => Ok(Format::Text),
+            other => Err(std::format!(
+                "unknown format {:?}, expected json, hex, native or text",
+                other
+            )),
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        f.write_str(match self {
+            Format::Json => "json",
+            Format::Hex => "hex",
+            Format::Native => "native",
+            Format::Text => "text",
+        })
+    }
+}
+
+fn decode_hex(line: &str) -> Result<Vec<u8>, CliError> {
+    let digits: Vec<u8> = line
+        .bytes()
+        .filter(|digit| !digit.is_ascii_whitespace())
+        .collect();
+    if digits.len() % 2 != 0 {
+        return Err(CliError::Hex(line.to_string()));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            core::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| CliError::Hex(line.to_string()))
+        })
+        .collect()
+}
+
+/// Reads one length prefixed frame, `None` when `reader` ends between frames
+fn read_frame<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>, CliError> {
+    let mut prefix = [0_u8; FRAME_PREFIX_LEN];
+    let mut filled = 0;
+    while filled < prefix.len() {
+        match reader.read(&mut prefix[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            read => filled += read,
+        }
+    }
+    let mut frame = std::vec![0_u8; frame_len(prefix)];
+    reader.read_exact(&mut frame)?;
+    Ok(Some(frame))
+}
+
+type Commands<'a> = Box<dyn Iterator<Item = Result<Command<Vec<u8>>, CliError>> + 'a>;
+
+/// Iterates over commands in `reader` written in `format`
+pub fn read_commands<'a, R>(format: Format, mut reader: R) -> Result<Commands<'a>, CliError>
+where
+    R: BufRead + 'a,
+{
+    Ok(match format {
+        Format::Json => Box::new(
+            serde_json::Deserializer::from_reader(reader)
+                .into_iter()
+                .map(|command| command.map_err(CliError::from)),
+        ),
+        Format::Hex => Box::new(
+            reader
+                .lines()
+                .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+                .map(|line| {
+                    let bytes = decode_hex(&line?)?;
+                    Ok(Command::try_from(&bytes[..])?)
+                }),
+        ),
+        Format::Native => Box::new(core::iter::from_fn(move || {
+            read_frame(&mut reader)
+                .transpose()
+                .map(|frame| Ok(Command::try_from(&frame?[..])?))
+        })),
+        Format::Text => return Err(CliError::Unsupported(format)),
+    })
+}
+
+/// Writes `command` to `writer` in `format`
+pub fn write_command<T, W>(
+    format: Format,
+    command: &Command<T>,
+    writer: &mut W,
+) -> Result<(), CliError>
+where
+    T: AsRef<[u8]>,
+    W: Write + ?Sized,
+{
+    match format {
+        Format::Json => {
+            serde_json::to_writer(&mut *writer, command)?;
+            writeln!(writer)?;
+        }
+        Format::Hex => {
+            let encoded = command.encoded();
+            writeln!(writer, "{}{}", Hex(encoded.head()), Hex(encoded.payload()))?;
+        }
+        Format::Native => {
+            let prefix =
+                frame_prefix(command.size_in_bytes()).ok_or(CommandError::ValueOutOfRange)?;
+            writer.write_all(&prefix)?;
+            for part in command.encoded().parts().iter() {
+                writer.write_all(part)?;
+            }
+        }
+        Format::Text => write!(writer, "{}", command)?,
+    }
+    Ok(())
+}
+
+/// Where `send` delivers commands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Standard output, `-`
+    Stdout,
+    /// `tcp://host:port`, length prefixed frames
+    Tcp(String),
+    /// `udp://host:port`, one command per datagram
+    Udp(String),
+    /// Any other path, e.g. `/dev/ttyUSB0`, length prefixed frames.
+    /// Baud rate and line settings are left to the device (`stty`).
+    Serial(PathBuf),
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "-" {
+            Ok(Target::Stdout)
+        } else if let Some(addr) = s.strip_prefix("tcp://") {
+            Ok(Target::Tcp(addr.to_string()))
+        } else if let Some(addr) = s.strip_prefix("udp://") {
+            Ok(Target::Udp(addr.to_string()))
+        } else if s.is_empty() {
+            Err("empty target".to_string())
+        } else {
+            Ok(Target::Serial(PathBuf::from(s)))
+        }
+    }
+}
+
+/// Open connection to a [`Target`]
+pub enum Sink {
+    Stream(Box<dyn Write>, Format),
+    Datagram(UdpSocket),
+}
+
+impl Sink {
+    /// Connects to `target`, `stdout_format` is used when the target is standard output
+    pub fn open(target: &Target, stdout_format: Format) -> Result<Self, CliError> {
+        Ok(match target {
+            Target::Stdout => Sink::Stream(Box::new(io::stdout()), stdout_format),
+            Target::Tcp(addr) => Sink::Stream(Box::new(TcpStream::connect(addr)?), Format::Native),
+            Target::Udp(addr) => {
+                let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+                socket.connect(addr)?;
+                Sink::Datagram(socket)
+            }
+            Target::Serial(path) => Sink::Stream(
+                Box::new(OpenOptions::new().write(true).open(path)?),
+                Format::Native,
+            ),
+        })
+    }
+
+    pub fn send<T: AsRef<[u8]>>(&mut self, command: &Command<T>) -> Result<(), CliError> {
+        match self {
+            Sink::Stream(writer, format) => write_command(*format, command, writer),
+            Sink::Datagram(socket) => {
+                let mut buf = std::vec![0_u8; command.size_in_bytes()];
+                command.try_write_bytes(&mut buf)?;
+                socket.send(&buf)?;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), CliError> {
+        if let Sink::Stream(writer, _) = self {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_commands, write_command, CliError, Format, Sink, Target};
+    use crate::Command;
+    extern crate std;
+    use std::{
+        io::{Cursor, Read},
+        net::{TcpListener, UdpSocket},
+        path::PathBuf,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+
+    fn commands() -> Vec<Command<Vec<u8>>> {
+        vec![
+            Command::Health,
+            Command::Constant {
+                led_count: 257,
+                colour: (254, 0, 254),
+            },
+            Command::Stream(vec![1, 2, 3]),
+            Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            },
+        ]
+    }
+
+    fn convert(from: Format, to: Format, input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        for command in read_commands(from, Cursor::new(input)).unwrap() {
+            write_command(to, &command.unwrap(), &mut output).unwrap();
+        }
+        output
+    }
+
+    #[test]
+    fn converts_between_formats() {
+        let json = b"{\"type\":\"health\"}\n{\"type\":\"stream\",\"bytes\":[1,2,3]}";
+        let hex = convert(Format::Json, Format::Hex, json);
+        assert_eq!(String::from_utf8(hex.clone()).unwrap(), "68\n73010203\n");
+        let native = convert(Format::Hex, Format::Native, &hex);
+        assert_eq!(native, [0, 1, b'h', 0, 4, b's', 1, 2, 3]);
+        let back = convert(Format::Native, Format::Json, &native);
+        assert_eq!(
+            String::from_utf8(back).unwrap(),
+            "{\"type\":\"health\"}\n{\"type\":\"stream\",\"bytes\":[1,2,3]}\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_every_format() {
+        for format in [Format::Json, Format::Hex, Format::Native].iter() {
+            let mut encoded = Vec::new();
+            for command in commands() {
+                write_command(*format, &command, &mut encoded).unwrap();
+            }
+            let decoded: Vec<_> = read_commands(*format, Cursor::new(encoded))
+                .unwrap()
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(decoded, commands(), "{}", format);
+        }
+    }
+
+    #[test]
+    fn pretty_prints_with_display() {
+        let text = convert(Format::Hex, Format::Text, b"630101ff0000\n");
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "Command::Constant -> (255, 0, 0) x 257\r\n"
+        );
+    }
+
+    #[test]
+    fn given_bad_input_fails() {
+        let outcome = read_commands(Format::Hex, Cursor::new("6\n"))
+            .unwrap()
+            .next()
+            .unwrap();
+        assert!(matches!(outcome, Err(CliError::Hex(_))));
+        let outcome = read_commands(Format::Native, Cursor::new([0, 2, b'x', 0]))
+            .unwrap()
+            .next()
+            .unwrap();
+        assert!(matches!(outcome, Err(CliError::Command(_))));
+        assert!(matches!(
+            read_commands(Format::Text, Cursor::new("")),
+            Err(CliError::Unsupported(Format::Text))
+        ));
+    }
+
+    #[test]
+    fn parses_targets() {
+        assert_eq!("-".parse(), Ok(Target::Stdout));
+        assert_eq!(
+            "tcp://127.0.0.1:9000".parse(),
+            Ok(Target::Tcp("127.0.0.1:9000".into()))
+        );
+        assert_eq!(
+            "udp://localhost:9000".parse(),
+            Ok(Target::Udp("localhost:9000".into()))
+        );
+        assert_eq!(
+            "/dev/ttyUSB0".parse(),
+            Ok(Target::Serial(PathBuf::from("/dev/ttyUSB0")))
+        );
+    }
+
+    #[test]
+    fn sends_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = Target::Tcp(listener.local_addr().unwrap().to_string());
+        let mut sink = Sink::open(&target, Format::Json).unwrap();
+        for command in commands() {
+            sink.send(&command).unwrap();
+        }
+        drop(sink);
+        let mut received = Vec::new();
+        listener
+            .accept()
+            .unwrap()
+            .0
+            .read_to_end(&mut received)
+            .unwrap();
+        let decoded: Vec<_> = read_commands(Format::Native, Cursor::new(received))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(decoded, commands());
+    }
+
+    #[test]
+    fn sends_over_udp() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = Target::Udp(socket.local_addr().unwrap().to_string());
+        let mut sink = Sink::open(&target, Format::Json).unwrap();
+        sink.send(&Command::Stream([1_u8, 2, 3])).unwrap();
+        let mut buf = [0_u8; 16];
+        let len = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[b's', 1, 2, 3]);
+    }
+
+    #[test]
+    fn sends_to_device_path() {
+        // Regular file stands in for a serial device
+        let path = std::env::temp_dir().join(std::format!("command-cli-{}", std::process::id()));
+        std::fs::write(&path, b"").unwrap();
+        let mut sink = Sink::open(&Target::Serial(path.clone()), Format::Json).unwrap();
+        sink.send(&Command::<&[u8]>::Health).unwrap();
+        sink.flush().unwrap();
+        drop(sink);
+        assert_eq!(std::fs::read(&path).unwrap(), [0, 1, b'h']);
+        std::fs::remove_file(path).unwrap();
+    }
+}
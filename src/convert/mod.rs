@@ -0,0 +1,151 @@
+use crate::Command;
+use core::convert::Infallible;
+
+impl<T> Command<T> {
+    /// Converts `Stream` payload with `f`, other variants are carried over as is
+    pub fn map<U, F>(self, f: F) -> Command<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self.try_map(|payload| Ok::<_, Infallible>(f(payload))) {
+            Ok(command) => command,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Fallible counterpart of [`Command::map`], `f` is only called for `Stream`
+    pub fn try_map<U, E, F>(self, f: F) -> Result<Command<U>, E>
+    where
+        F: FnOnce(T) -> Result<U, E>,
+    {
+        Ok(match self {
+            Command::Health => Command::Health,
+            Command::Constant { led_count, colour } => Command::Constant { led_count, colour },
+            Command::Stream(payload) => Command::Stream(f(payload)?),
+            Command::Pulse {
+                led_count,
+                start,
+                end,
+                frames,
+                period,
+            } => Command::Pulse {
+                led_count,
+                start,
+                end,
+                frames,
+                period,
+            },
+        })
+    }
+}
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Borrows the payload, so any storage can go where `Command<&[u8]>` is expected
+    pub fn as_ref(&self) -> Command<&[u8]> {
+        match self {
+            Command::Health => Command::Health,
+            Command::Constant { led_count, colour } => Command::Constant {
+                led_count: *led_count,
+                colour: *colour,
+            },
+            Command::Stream(payload) => Command::Stream(payload.as_ref()),
+            Command::Pulse {
+                led_count,
+                start,
+                end,
+                frames,
+                period,
+            } => Command::Pulse {
+                led_count: *led_count,
+                start: *start,
+                end: *end,
+                frames: *frames,
+                period: *period,
+            },
+        }
+    }
+}
+
+impl<T> Command<T>
+where
+    T: AsMut<[u8]>,
+{
+    /// Mutably borrows the payload
+    pub fn as_mut(&mut self) -> Command<&mut [u8]> {
+        match self {
+            Command::Health => Command::Health,
+            Command::Constant { led_count, colour } => Command::Constant {
+                led_count: *led_count,
+                colour: *colour,
+            },
+            Command::Stream(payload) => Command::Stream(payload.as_mut()),
+            Command::Pulse {
+                led_count,
+                start,
+                end,
+                frames,
+                period,
+            } => Command::Pulse {
+                led_count: *led_count,
+                start: *start,
+                end: *end,
+                frames: *frames,
+                period: *period,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Command;
+    extern crate std;
+    use std::collections::HashSet;
+
+    fn pulse<T>() -> Command<T> {
+        Command::Pulse {
+            led_count: 300,
+            start: (0, 0, 0),
+            end: (255, 0, 0),
+            frames: 60,
+            period: 1000,
+        }
+    }
+
+    #[test]
+    fn maps_stream_payload() {
+        let cmd = Command::Stream([1_u8, 2, 3]).map(|payload| payload.len());
+        assert_eq!(cmd, Command::Stream(3));
+        assert_eq!(pulse::<[u8; 3]>().map(|payload| payload.len()), pulse());
+    }
+
+    #[test]
+    fn try_map_only_calls_f_for_stream() {
+        let outcome = pulse::<[u8; 3]>().try_map(|_| Err(()));
+        assert_eq!(outcome, Ok(pulse::<()>()));
+        let outcome = Command::Stream([1_u8, 2, 3]).try_map(|_| Err::<(), _>("full"));
+        assert_eq!(outcome, Err("full"));
+    }
+
+    #[test]
+    fn borrows_any_storage() {
+        let mut cmd = Command::Stream([1_u8, 2, 3]);
+        assert_eq!(cmd.as_ref(), Command::Stream(&[1_u8, 2, 3][..]));
+        if let Command::Stream(payload) = cmd.as_mut() {
+            payload[0] = 9;
+        }
+        assert_eq!(cmd, Command::Stream([9, 2, 3]));
+        assert_eq!(pulse::<[u8; 3]>().as_ref(), pulse());
+    }
+
+    #[test]
+    fn borrowed_commands_are_copy_and_hash() {
+        let cmd = Command::Stream(&[1_u8, 2, 3][..]);
+        let copy = cmd;
+        let commands: HashSet<_> = [cmd, copy, pulse()].iter().copied().collect();
+        assert_eq!(commands.len(), 2);
+    }
+}
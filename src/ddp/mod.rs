@@ -0,0 +1,353 @@
+#![cfg(feature = "ddp_impl")]
+//! Distributed Display Protocol packets, as sent by xLights, WLED and other pixel mapping tools.
+//!
+//! A frame is split into packets carrying data at a byte offset, the packet with the push flag
+//! tells the display to show what it got. Usually sent over UDP port 4048.
+use crate::{Command, CommandError};
+use core::{convert::TryFrom, iter::FusedIterator};
+
+pub const PORT: u16 = 4048;
+pub const HEADER_LEN: usize = 10;
+/// Timecode following the header when [`TIMECODE`] is set
+pub const TIMECODE_LEN: usize = 4;
+/// Most data senders put into a packet, 480 RGB pixels
+pub const MAX_DATA_LEN: usize = 1440;
+
+/// Flags of the first header byte
+pub const PUSH: u8 = 0x01;
+pub const QUERY: u8 = 0x02;
+pub const REPLY: u8 = 0x04;
+pub const STORAGE: u8 = 0x08;
+pub const TIMECODE: u8 = 0x10;
+const VERSION_MASK: u8 = 0xc0;
+const VERSION_1: u8 = 0x40;
+
+/// Data type of 8 bit RGB pixels
+pub const RGB8: u8 = 0x0b;
+/// Destination of display data
+pub const DEFAULT_OUTPUT: u8 = 0x01;
+/// Destination every device accepts
+pub const ALL_DEVICES: u8 = 0xff;
+
+/// One DDP packet, data borrows from the parsed buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet<'a> {
+    /// [`PUSH`], [`QUERY`] and friends, version bits are checked and set by the crate
+    pub flags: u8,
+    /// 1 to 15 to detect duplicates, 0 when unused
+    pub sequence: u8,
+    pub data_type: u8,
+    pub destination: u8,
+    /// Offset of `data` within the frame, in bytes
+    pub offset: u32,
+    pub data: &'a [u8],
+}
+
+impl<'a> Packet<'a> {
+    /// Packet of RGB data for the default output
+    pub fn rgb(offset: u32, data: &'a [u8], push: bool) -> Self {
+        Self {
+            flags: if push { PUSH } else { 0 },
+            sequence: 0,
+            data_type: RGB8,
+            destination: DEFAULT_OUTPUT,
+            offset,
+            data,
+        }
+    }
+
+    pub fn is_push(&self) -> bool {
+        self.flags & PUSH != 0
+    }
+
+    /// Fails with `InvalidHeader` for versions other than 1 and `MalformedPayload` when `bytes`
+    /// end before the data does. Timecode is skipped.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, CommandError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(CommandError::MalformedPayload);
+        }
+        if bytes[0] & VERSION_MASK != VERSION_1 {
+            return Err(CommandError::InvalidHeader);
+        }
+        let flags = bytes[0] & !VERSION_MASK;
+        let start = if flags & TIMECODE != 0 {
+            HEADER_LEN + TIMECODE_LEN
+        } else {
+            HEADER_LEN
+        };
+        let len = u16::from_be_bytes([bytes[8], bytes[9]]) as usize;
+        let data = bytes
+            .get(start..start + len)
+            .ok_or(CommandError::MalformedPayload)?;
+        Ok(Self {
+            flags,
+            sequence: bytes[1] & 0x0f,
+            data_type: bytes[2],
+            destination: bytes[3],
+            offset: u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            data,
+        })
+    }
+
+    /// Writes the packet without timecode, returns number of bytes written.
+    /// Fails with `ValueOutOfRange` when data overflows the `u16` length
+    pub fn try_write(&self, buf: &mut [u8]) -> Result<usize, CommandError> {
+        if self.data.len() > u16::MAX as usize {
+            return Err(CommandError::ValueOutOfRange);
+        }
+        let len = HEADER_LEN + self.data.len();
+        let buf = buf.get_mut(..len).ok_or(CommandError::BufferTooSmall)?;
+        buf[0] = VERSION_1 | (self.flags & !(VERSION_MASK | TIMECODE));
+        buf[1] = self.sequence & 0x0f;
+        buf[2] = self.data_type;
+        buf[3] = self.destination;
+        buf[4..8].copy_from_slice(&self.offset.to_be_bytes());
+        buf[8..10].copy_from_slice(&(self.data.len() as u16).to_be_bytes());
+        buf[HEADER_LEN..].copy_from_slice(self.data);
+        Ok(len)
+    }
+}
+
+/// Packets of a `Stream` payload, see [`Command::ddp_packets`]
+#[derive(Debug, Clone)]
+pub struct Packets<'a> {
+    data: &'a [u8],
+    offset: usize,
+    sequence: u8,
+    done: bool,
+}
+
+impl<'a> Iterator for Packets<'a> {
+    type Item = Packet<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let end = (self.offset + MAX_DATA_LEN).min(self.data.len());
+        self.done = end == self.data.len();
+        let mut packet = Packet::rgb(self.offset as u32, &self.data[self.offset..end], self.done);
+        packet.sequence = self.sequence;
+        self.offset = end;
+        Some(packet)
+    }
+}
+
+impl FusedIterator for Packets<'_> {}
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Splits `Stream` payload into packets of up to [`MAX_DATA_LEN`] bytes, the last one pushing the frame.
+    /// Packets borrow the payload, so other commands fail with `InvalidHeader`
+    pub fn ddp_packets(&self, sequence: u8) -> Result<Packets<'_>, CommandError> {
+        match self {
+            Command::Stream(bytes) => Ok(Packets {
+                data: bytes.as_ref(),
+                offset: 0,
+                sequence: sequence & 0x0f,
+                done: false,
+            }),
+            _ => Err(CommandError::InvalidHeader),
+        }
+    }
+}
+
+/// Collects display data packets into a frame stored in `B`
+#[derive(Debug, Clone)]
+pub struct DdpAssembler<B> {
+    buf: B,
+    /// End of the furthest data received
+    len: usize,
+}
+
+impl<B> DdpAssembler<B>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Frames of up to `buf.len()` bytes are assembled
+    pub fn new(buf: B) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Copies `packet` data at its offset, returns the frame once a packet with the push flag arrives.
+    /// The frame spans the furthest data received since the previous push, cut to whole pixels.
+    ///
+    /// Queries, replies and packets for other destinations are ignored,
+    /// data reaching past the buffer fails with `BufferTooSmall` and is dropped
+    pub fn handle(&mut self, packet: &Packet<'_>) -> Result<Option<Command<&[u8]>>, CommandError> {
+        if packet.flags & (QUERY | REPLY) != 0
+            || ![DEFAULT_OUTPUT, ALL_DEVICES].contains(&packet.destination)
+        {
+            return Ok(None);
+        }
+        // Offsets come from the network, keep them from overflowing on 32 bit targets
+        let range = usize::try_from(packet.offset)
+            .ok()
+            .and_then(|start| Some(start..start.checked_add(packet.data.len())?))
+            .ok_or(CommandError::BufferTooSmall)?;
+        self.buf
+            .as_mut()
+            .get_mut(range.clone())
+            .ok_or(CommandError::BufferTooSmall)?
+            .copy_from_slice(packet.data);
+        self.len = self.len.max(range.end);
+
+        if packet.is_push() {
+            let len = self.len - self.len % 3;
+            self.len = 0;
+            Ok(Some(Command::Stream(&self.buf.as_ref()[..len])))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DdpAssembler, Packet, HEADER_LEN, MAX_DATA_LEN, PUSH, QUERY, RGB8};
+    use crate::{Command, CommandError};
+    extern crate std;
+    use std::{vec, vec::Vec};
+
+    /// Synthetic frame laid out by hand from the DDP spec, not a capture.
+    /// 3 LEDs split over two packets, sequence 3
+    const SPEC_PACKETS: [&[u8]; 2] = [
+        &[
+            0x40, 0x03, 0x0b, 0x01, 0, 0, 0, 0, 0x00, 0x06, 255, 0, 0, 0, 255, 0,
+        ],
+        &[0x41, 0x03, 0x0b, 0x01, 0, 0, 0, 6, 0x00, 0x03, 0, 0, 255],
+    ];
+
+    /// Synthetic single pushed LED with timecode
+    const SPEC_TIMECODE: [u8; 17] = [
+        0x51, 0x00, 0x0b, 0x01, 0, 0, 0, 0, 0x00, 0x03, 0, 0, 0x12, 0x34, 9, 8, 7,
+    ];
+
+    #[test]
+    fn parses_spec_packets() {
+        let first = Packet::parse(SPEC_PACKETS[0]).unwrap();
+        assert!(!first.is_push());
+        assert_eq!(first.sequence, 3);
+        assert_eq!(first.data_type, RGB8);
+        assert_eq!(first.data, &[255, 0, 0, 0, 255, 0]);
+        let second = Packet::parse(SPEC_PACKETS[1]).unwrap();
+        assert!(second.is_push());
+        assert_eq!(second.offset, 6);
+        let timecode = Packet::parse(&SPEC_TIMECODE).unwrap();
+        assert_eq!(timecode.data, &[9, 8, 7]);
+    }
+
+    #[test]
+    fn given_broken_packet_fails() {
+        assert_eq!(
+            Packet::parse(&SPEC_PACKETS[1][..9]),
+            Err(CommandError::MalformedPayload)
+        );
+        assert_eq!(
+            Packet::parse(&SPEC_PACKETS[1][..12]),
+            Err(CommandError::MalformedPayload)
+        );
+        let mut version_2 = SPEC_PACKETS[1].to_vec();
+        version_2[0] = 0x81;
+        assert_eq!(Packet::parse(&version_2), Err(CommandError::InvalidHeader));
+    }
+
+    #[test]
+    fn assembles_frame_on_push() {
+        let mut assembler = DdpAssembler::new([0_u8; 12]);
+        let first = Packet::parse(SPEC_PACKETS[0]).unwrap();
+        assert_eq!(assembler.handle(&first), Ok(None));
+        let second = Packet::parse(SPEC_PACKETS[1]).unwrap();
+        assert_eq!(
+            assembler.handle(&second),
+            Ok(Some(Command::Stream(
+                &[255, 0, 0, 0, 255, 0, 0, 0, 255][..]
+            )))
+        );
+        let query = Packet {
+            flags: QUERY | PUSH,
+            ..second
+        };
+        assert_eq!(assembler.handle(&query), Ok(None));
+        let past = Packet::rgb(9, &[1; 6], true);
+        assert_eq!(assembler.handle(&past), Err(CommandError::BufferTooSmall));
+        let overflowing = Packet::rgb(u32::MAX, &[1; 6], true);
+        assert_eq!(
+            assembler.handle(&overflowing),
+            Err(CommandError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn given_shorter_frame_drops_old_tail() {
+        let mut assembler = DdpAssembler::new([0_u8; 12]);
+        let long = Packet::rgb(0, &[1; 12], true);
+        assert!(assembler.handle(&long).unwrap().is_some());
+        let short = Packet::rgb(0, &[2; 6], true);
+        assert_eq!(
+            assembler.handle(&short),
+            Ok(Some(Command::Stream(&[2; 6][..])))
+        );
+    }
+
+    #[test]
+    fn writes_spec_packets() {
+        let mut buf = [0_u8; 32];
+        let mut first = Packet::rgb(0, &[255, 0, 0, 0, 255, 0], false);
+        first.sequence = 3;
+        assert_eq!(first.try_write(&mut buf), Ok(16));
+        assert_eq!(&buf[..16], SPEC_PACKETS[0]);
+        let mut second = Packet::rgb(6, &[0, 0, 255], true);
+        second.sequence = 3;
+        assert_eq!(second.try_write(&mut buf), Ok(13));
+        assert_eq!(&buf[..13], SPEC_PACKETS[1]);
+    }
+
+    #[test]
+    fn encodes_stream_like_golden_file() {
+        // 482 LEDs of bytes counting up, sequence 5, packets written back to back by a script
+        // independent of this crate
+        let golden = include_bytes!("golden_packets.bin");
+        let pixels: Vec<u8> = (0..482 * 3).map(|idx| idx as u8).collect();
+        let stream = Command::Stream(&pixels[..]);
+        let mut buf = [0_u8; HEADER_LEN + MAX_DATA_LEN];
+        let mut encoded = Vec::new();
+        for packet in stream.ddp_packets(5).unwrap() {
+            let len = packet.try_write(&mut buf).unwrap();
+            encoded.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(encoded, &golden[..]);
+    }
+
+    #[test]
+    fn splits_long_streams() {
+        let pixels = vec![7_u8; MAX_DATA_LEN * 2 + 3];
+        let stream = Command::Stream(&pixels[..]);
+        let packets: Vec<_> = stream.ddp_packets(1).unwrap().collect();
+        let layout: Vec<_> = packets
+            .iter()
+            .map(|packet| (packet.offset, packet.data.len(), packet.is_push()))
+            .collect();
+        assert_eq!(
+            layout,
+            [(0, 1440, false), (1440, 1440, false), (2880, 3, true)]
+        );
+
+        let mut assembler = DdpAssembler::new(vec![0_u8; pixels.len()]);
+        let mut buf = vec![0_u8; MAX_DATA_LEN + 10];
+        let mut frames = Vec::new();
+        for packet in packets {
+            let len = packet.try_write(&mut buf).unwrap();
+            if let Some(Command::Stream(frame)) = assembler
+                .handle(&Packet::parse(&buf[..len]).unwrap())
+                .unwrap()
+            {
+                frames.push(frame.to_vec());
+            }
+        }
+        assert_eq!(frames, [pixels]);
+        assert!(Command::<&[u8]>::Health.ddp_packets(0).is_err());
+    }
+}
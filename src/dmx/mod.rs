@@ -0,0 +1,198 @@
+//! Assembles `Stream` frames from DMX universes for strips too long for a single universe.
+extern crate std;
+use crate::Command;
+use std::{vec, vec::Vec};
+
+/// Channels in a DMX universe
+pub const UNIVERSE_LEN: usize = 512;
+
+/// RGB LEDs that fit into a universe, xLights and most pixel controllers leave the last 2 channels unused
+pub const MAX_LEDS_PER_UNIVERSE: usize = UNIVERSE_LEN / 3;
+
+/// Sequence numbers wrap around, less than 20 behind `last` counts as out of order (ANSI E1.31 6.7.2)
+pub(crate) fn is_out_of_order(last: u8, sequence: u8) -> bool {
+    (-19..=0).contains(&(sequence.wrapping_sub(last) as i8))
+}
+
+/// Which universes carry which LEDs.
+/// Universe `first_universe + n` starts at LED `n * leds_per_universe`, channel 1 being red of that LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    first_universe: u16,
+    leds: usize,
+    leds_per_universe: usize,
+}
+
+impl Layout {
+    /// Packs [`MAX_LEDS_PER_UNIVERSE`] LEDs into every universe
+    pub fn new(first_universe: u16, leds: usize) -> Self {
+        Self {
+            first_universe,
+            leds,
+            leds_per_universe: MAX_LEDS_PER_UNIVERSE,
+        }
+    }
+
+    /// Clamped to `1..=MAX_LEDS_PER_UNIVERSE`
+    pub fn with_leds_per_universe(mut self, leds_per_universe: usize) -> Self {
+        self.leds_per_universe = leds_per_universe.clamp(1, MAX_LEDS_PER_UNIVERSE);
+        self
+    }
+
+    pub fn first_universe(&self) -> u16 {
+        self.first_universe
+    }
+
+    pub fn leds(&self) -> usize {
+        self.leds
+    }
+
+    pub fn leds_per_universe(&self) -> usize {
+        self.leds_per_universe
+    }
+
+    /// Universes the strip spans, cut short at `u16::MAX` so LEDs past it are never waited for
+    pub fn universe_count(&self) -> usize {
+        let available = u16::MAX as usize - self.first_universe as usize + 1;
+        self.leds.div_ceil(self.leds_per_universe).min(available)
+    }
+
+    pub fn universes(&self) -> impl Iterator<Item = u16> {
+        (self.first_universe..=u16::MAX).take(self.universe_count())
+    }
+
+    /// Position of `universe` within the layout
+    pub(crate) fn index(&self, universe: u16) -> Option<usize> {
+        let index = universe.checked_sub(self.first_universe)? as usize;
+        (index < self.universe_count()).then_some(index)
+    }
+}
+
+/// Collects universes of a [`Layout`] into one `Stream` payload
+#[derive(Debug, Clone)]
+pub struct Assembler {
+    layout: Layout,
+    pixels: Vec<u8>,
+    updated: Vec<bool>,
+}
+
+impl Assembler {
+    /// Starts with every LED off
+    pub fn new(layout: Layout) -> Self {
+        Self {
+            layout,
+            pixels: vec![0; layout.leds() * 3],
+            updated: vec![false; layout.universe_count()],
+        }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Copies DMX `channels` of `universe` into the frame, universes outside the layout are ignored.
+    ///
+    /// Returns `true` once every universe of the layout has been updated since the last complete frame.
+    /// A universe updated twice before that also completes the frame, so a lost packet delays the
+    /// frame by one refresh instead of stalling it.
+    pub fn update(&mut self, universe: u16, channels: &[u8]) -> bool {
+        let index = match self.layout.index(universe) {
+            Some(index) => index,
+            None => return false,
+        };
+        let repeated = self.updated[index];
+        if repeated {
+            self.updated.iter_mut().for_each(|updated| *updated = false);
+        }
+
+        let first_led = index * self.layout.leds_per_universe();
+        let last_led = (first_led + self.layout.leds_per_universe()).min(self.layout.leds());
+        let target = &mut self.pixels[first_led * 3..last_led * 3];
+        let len = target.len().min(channels.len());
+        target[..len].copy_from_slice(&channels[..len]);
+        self.updated[index] = true;
+
+        if repeated || self.updated.iter().all(|updated| *updated) {
+            self.updated.iter_mut().for_each(|updated| *updated = false);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Latest colours of every LED
+    pub fn frame(&self) -> Command<&[u8]> {
+        Command::Stream(&self.pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_out_of_order, Assembler, Layout};
+    use crate::Command;
+    extern crate std;
+    use std::vec::Vec;
+
+    #[test]
+    fn spans_universes() {
+        let layout = Layout::new(1, 400);
+        assert_eq!(layout.universe_count(), 3);
+        assert_eq!(layout.universes().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(layout.index(0), None);
+        assert_eq!(layout.index(3), Some(2));
+        assert_eq!(layout.index(4), None);
+        let layout = Layout::new(u16::MAX, 400).with_leds_per_universe(500);
+        assert_eq!(layout.leds_per_universe(), 170);
+        assert_eq!(layout.universe_count(), 1);
+        assert_eq!(layout.universes().collect::<Vec<_>>(), [u16::MAX]);
+    }
+
+    #[test]
+    fn completes_frame_at_top_of_universe_range() {
+        let mut assembler = Assembler::new(Layout::new(u16::MAX - 1, 6).with_leds_per_universe(2));
+        assert!(!assembler.update(u16::MAX - 1, &[1; 6]));
+        assert!(assembler.update(u16::MAX, &[2; 6]));
+        assert_eq!(
+            assembler.frame(),
+            Command::Stream(&[1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 0, 0, 0, 0, 0, 0][..])
+        );
+    }
+
+    #[test]
+    fn detects_out_of_order_sequence() {
+        assert!(is_out_of_order(10, 10));
+        assert!(is_out_of_order(10, 0));
+        assert!(!is_out_of_order(10, 11));
+        assert!(!is_out_of_order(255, 0));
+        assert!(!is_out_of_order(250, 200));
+        // 20 behind is taken as a restarted sender, 19 behind as a late packet
+        assert!(!is_out_of_order(30, 10));
+        assert!(is_out_of_order(30, 11));
+        assert!(!is_out_of_order(5, 241));
+        assert!(is_out_of_order(5, 242));
+    }
+
+    #[test]
+    fn completes_frame_once_every_universe_arrived() {
+        let mut assembler = Assembler::new(Layout::new(5, 3).with_leds_per_universe(2));
+        assert!(!assembler.update(5, &[1, 1, 1, 2, 2, 2, 9, 9]));
+        assert!(!assembler.update(7, &[9; 6]));
+        assert!(assembler.update(6, &[3, 3, 3, 9, 9, 9]));
+        assert_eq!(
+            assembler.frame(),
+            Command::Stream(&[1, 1, 1, 2, 2, 2, 3, 3, 3][..])
+        );
+    }
+
+    #[test]
+    fn given_lost_universe_repeat_completes_frame() {
+        let mut assembler = Assembler::new(Layout::new(1, 4).with_leds_per_universe(2));
+        assert!(!assembler.update(1, &[1; 6]));
+        assert!(assembler.update(1, &[2; 3]));
+        assert_eq!(
+            assembler.frame().pixels().unwrap().collect::<Vec<_>>(),
+            [(2, 2, 2), (1, 1, 1), (0, 0, 0), (0, 0, 0)]
+        );
+        assert!(!assembler.update(2, &[3; 6]));
+    }
+}
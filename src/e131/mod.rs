@@ -0,0 +1,467 @@
+#![cfg(feature = "e131_impl")]
+//! E1.31 (sACN) receiver turning DMX universes into `Stream` frames.
+//!
+//! Only data packets are understood, synchronization and universe discovery packets are ignored.
+//! Of several sources sending the same universe the one with the highest priority wins,
+//! sources of equal priority take turns.
+extern crate std;
+use crate::{
+    dmx::{is_out_of_order, Assembler, Layout, UNIVERSE_LEN},
+    Command,
+};
+use core::{
+    fmt::{Display, Formatter, Result as FMTResult},
+    str,
+};
+use std::{
+    io,
+    net::{Ipv4Addr, UdpSocket},
+    time::{Duration, Instant},
+    vec,
+    vec::Vec,
+};
+
+/// UDP port E1.31 is sent to
+pub const PORT: u16 = 5568;
+
+/// Source silent for this long is forgotten, letting lower priority sources take over
+pub const SOURCE_TIMEOUT: Duration = Duration::from_millis(2500);
+
+/// Largest data packet, with a full universe
+pub const MAX_PACKET_LEN: usize = DATA_OFFSET + UNIVERSE_LEN;
+
+const ACN_IDENTIFIER: &[u8; 12] = b"ASC-E1.17\0\0\0";
+const VECTOR_ROOT_DATA: u32 = 0x0000_0004;
+const VECTOR_FRAMING_DATA: u32 = 0x0000_0002;
+const VECTOR_DMP_SET_PROPERTY: u8 = 0x02;
+const DMP_ADDRESS_DATA_TYPE: u8 = 0xa1;
+const SOURCE_NAME_LEN: usize = 64;
+const OPTION_PREVIEW: u8 = 0x80;
+const OPTION_STREAM_TERMINATED: u8 = 0x40;
+/// Offset of the DMX start code
+const START_CODE_OFFSET: usize = 125;
+const DATA_OFFSET: usize = START_CODE_OFFSET + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum E131Error {
+    /// Packet ends before its headers do, or its PDU lengths do not add up
+    Truncated,
+    /// Missing preamble or ACN packet identifier, not an E1.31 packet
+    NotE131,
+    /// Root or framing layer vector of a packet other than data, e.g. synchronization
+    UnsupportedVector(u32),
+    /// DMP layer does not describe consecutive one byte properties
+    InvalidDmp,
+}
+
+impl Display for E131Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        match self {
+            E131Error::Truncated => write!(f, "E1.31 packet is truncated"),
+            E131Error::NotE131 => write!(f, "Not an E1.31 packet"),
+            E131Error::UnsupportedVector(vector) => {
+                write!(f, "Unsupported E1.31 vector {:#010x}", vector)
+            }
+            E131Error::InvalidDmp => write!(f, "Invalid E1.31 DMP layer"),
+        }
+    }
+}
+
+impl std::error::Error for E131Error {}
+
+/// E1.31 data packet, DMX data borrows from the received buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataPacket<'a> {
+    /// Component identifier, a UUID unique to the sender
+    pub cid: [u8; 16],
+    pub source_name: &'a str,
+    /// 0 to 200, 100 being the default
+    pub priority: u8,
+    /// Universe synchronization packets are sent to, 0 when not synchronized
+    pub sync_address: u16,
+    pub sequence: u8,
+    /// Data is meant for visualisers only, not for output
+    pub preview: bool,
+    /// Sender stopped sending this universe
+    pub stream_terminated: bool,
+    pub universe: u16,
+    /// 0 for DMX levels
+    pub start_code: u8,
+    /// Channel values, up to 512
+    pub data: &'a [u8],
+}
+
+fn u16_at(packet: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([packet[offset], packet[offset + 1]])
+}
+
+fn u32_at(packet: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        packet[offset],
+        packet[offset + 1],
+        packet[offset + 2],
+        packet[offset + 3],
+    ])
+}
+
+/// Length of the PDU starting at `offset`, without the 0x7 flags
+fn pdu_len(packet: &[u8], offset: usize) -> usize {
+    (u16_at(packet, offset) & 0x0fff) as usize
+}
+
+fn flags_and_len(len: usize) -> [u8; 2] {
+    (0x7000 | len as u16).to_be_bytes()
+}
+
+impl<'a> DataPacket<'a> {
+    pub fn parse(packet: &'a [u8]) -> Result<Self, E131Error> {
+        if packet.len() < 22 {
+            return Err(E131Error::Truncated);
+        }
+        if u16_at(packet, 0) != 0x0010 || u16_at(packet, 2) != 0 || &packet[4..16] != ACN_IDENTIFIER
+        {
+            return Err(E131Error::NotE131);
+        }
+        let vector = u32_at(packet, 18);
+        if vector != VECTOR_ROOT_DATA {
+            return Err(E131Error::UnsupportedVector(vector));
+        }
+        if packet.len() < DATA_OFFSET {
+            return Err(E131Error::Truncated);
+        }
+        let vector = u32_at(packet, 40);
+        if vector != VECTOR_FRAMING_DATA {
+            return Err(E131Error::UnsupportedVector(vector));
+        }
+        // Every layer runs to the end of the packet
+        if 16 + pdu_len(packet, 16) != packet.len()
+            || 38 + pdu_len(packet, 38) != packet.len()
+            || 115 + pdu_len(packet, 115) != packet.len()
+        {
+            return Err(E131Error::Truncated);
+        }
+        if packet[117] != VECTOR_DMP_SET_PROPERTY
+            || packet[118] != DMP_ADDRESS_DATA_TYPE
+            || u16_at(packet, 119) != 0
+            || u16_at(packet, 121) != 1
+            || u16_at(packet, 123) as usize != packet.len() - START_CODE_OFFSET
+            || packet.len() > MAX_PACKET_LEN
+        {
+            return Err(E131Error::InvalidDmp);
+        }
+
+        let mut cid = [0_u8; 16];
+        cid.copy_from_slice(&packet[22..38]);
+        let name = &packet[44..44 + SOURCE_NAME_LEN];
+        let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+        let source_name = match str::from_utf8(name) {
+            Ok(name) => name,
+            Err(err) => str::from_utf8(&name[..err.valid_up_to()]).unwrap_or_default(),
+        };
+        let options = packet[112];
+        Ok(Self {
+            cid,
+            source_name,
+            priority: packet[108],
+            sync_address: u16_at(packet, 109),
+            sequence: packet[111],
+            preview: options & OPTION_PREVIEW != 0,
+            stream_terminated: options & OPTION_STREAM_TERMINATED != 0,
+            universe: u16_at(packet, 113),
+            start_code: packet[START_CODE_OFFSET],
+            data: &packet[DATA_OFFSET..],
+        })
+    }
+
+    /// Encodes the packet, source name is cut to 63 bytes and data to 512 channels
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data = &self.data[..self.data.len().min(UNIVERSE_LEN)];
+        let len = DATA_OFFSET + data.len();
+        let mut packet = vec![0_u8; len];
+        packet[0..2].copy_from_slice(&0x0010_u16.to_be_bytes());
+        packet[4..16].copy_from_slice(ACN_IDENTIFIER);
+        packet[16..18].copy_from_slice(&flags_and_len(len - 16));
+        packet[18..22].copy_from_slice(&VECTOR_ROOT_DATA.to_be_bytes());
+        packet[22..38].copy_from_slice(&self.cid);
+        packet[38..40].copy_from_slice(&flags_and_len(len - 38));
+        packet[40..44].copy_from_slice(&VECTOR_FRAMING_DATA.to_be_bytes());
+        let name = self.source_name.as_bytes();
+        let name = &name[..name.len().min(SOURCE_NAME_LEN - 1)];
+        packet[44..44 + name.len()].copy_from_slice(name);
+        packet[108] = self.priority;
+        packet[109..111].copy_from_slice(&self.sync_address.to_be_bytes());
+        packet[111] = self.sequence;
+        packet[112] = if self.preview { OPTION_PREVIEW } else { 0 }
+            | if self.stream_terminated {
+                OPTION_STREAM_TERMINATED
+            } else {
+                0
+            };
+        packet[113..115].copy_from_slice(&self.universe.to_be_bytes());
+        packet[115..117].copy_from_slice(&flags_and_len(len - 115));
+        packet[117] = VECTOR_DMP_SET_PROPERTY;
+        packet[118] = DMP_ADDRESS_DATA_TYPE;
+        packet[121..123].copy_from_slice(&1_u16.to_be_bytes());
+        packet[123..125].copy_from_slice(&((data.len() + 1) as u16).to_be_bytes());
+        packet[START_CODE_OFFSET] = self.start_code;
+        packet[DATA_OFFSET..].copy_from_slice(data);
+        packet
+    }
+}
+
+/// Multicast group `universe` is sent to
+pub fn multicast_addr(universe: u16) -> Ipv4Addr {
+    let [high, low] = universe.to_be_bytes();
+    Ipv4Addr::new(239, 255, high, low)
+}
+
+/// Source currently sending a universe
+#[derive(Debug, Clone, Copy)]
+struct Source {
+    cid: [u8; 16],
+    priority: u8,
+    sequence: u8,
+    seen: Instant,
+}
+
+/// Tracks sources of every universe in a [`Layout`] and assembles their data into frames
+#[derive(Debug, Clone)]
+pub struct Receiver {
+    assembler: Assembler,
+    sources: Vec<Option<Source>>,
+}
+
+impl Receiver {
+    pub fn new(layout: Layout) -> Self {
+        Self {
+            assembler: Assembler::new(layout),
+            sources: vec![None; layout.universe_count()],
+        }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        self.assembler.layout()
+    }
+
+    /// Handles a packet received at `now`, returns the frame it completes.
+    ///
+    /// Preview data, non DMX start codes, universes outside the layout, lower priority sources and
+    /// packets arriving out of order are ignored.
+    pub fn handle(
+        &mut self,
+        packet: &[u8],
+        now: Instant,
+    ) -> Result<Option<Command<&[u8]>>, E131Error> {
+        let packet = DataPacket::parse(packet)?;
+        let index = match self.layout().index(packet.universe) {
+            Some(index) if !packet.preview && packet.start_code == 0 => index,
+            _ => return Ok(None),
+        };
+
+        let source = &mut self.sources[index];
+        if let Some(current) = source {
+            let expired = now.saturating_duration_since(current.seen) >= SOURCE_TIMEOUT;
+            if current.cid == packet.cid {
+                if !expired && is_out_of_order(current.sequence, packet.sequence) {
+                    return Ok(None);
+                }
+            } else if !expired && packet.priority < current.priority {
+                return Ok(None);
+            }
+        }
+        if packet.stream_terminated {
+            *source = None;
+            return Ok(None);
+        }
+        *source = Some(Source {
+            cid: packet.cid,
+            priority: packet.priority,
+            sequence: packet.sequence,
+            seen: now,
+        });
+
+        if self.assembler.update(packet.universe, packet.data) {
+            Ok(Some(self.assembler.frame()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Receives packets from `socket` until one completes a frame, invalid packets are skipped
+    pub fn recv(&mut self, socket: &UdpSocket) -> io::Result<Command<&[u8]>> {
+        let mut buf = [0_u8; MAX_PACKET_LEN];
+        loop {
+            let len = socket.recv(&mut buf)?;
+            if let Ok(Some(_)) = self.handle(&buf[..len], Instant::now()) {
+                return Ok(self.assembler.frame());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{multicast_addr, DataPacket, E131Error, Receiver, SOURCE_TIMEOUT};
+    use crate::{dmx::Layout, Command};
+    use core::time::Duration;
+    extern crate std;
+    use std::{net::UdpSocket, time::Instant, vec::Vec};
+
+    /// Synthetic data packet laid out by hand from the root, framing and DMP layers of ANSI E1.31,
+    /// not a capture. Universe 1, 2 RGB pixels, sequence 0x2a, priority 100, source name "xLights"
+    const SPEC_PACKET: &str = "\
+        001000004153432d45312e3137000000\
+        7074000000041a2b3c4d5e6f70818293a4b5c6d7e8f9\
+        705e00000002784c6967687473000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000\
+        640000\
+        2a000001\
+        7011\
+        02a1000000010007\
+        00ff000000ff00";
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&text[idx..idx + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn packet(universe: u16, sequence: u8, priority: u8, data: &[u8]) -> Vec<u8> {
+        DataPacket {
+            cid: [7; 16],
+            source_name: "test",
+            priority,
+            sync_address: 0,
+            sequence,
+            preview: false,
+            stream_terminated: false,
+            universe,
+            start_code: 0,
+            data,
+        }
+        .to_bytes()
+    }
+
+    #[test]
+    fn parses_spec_packet() {
+        let bytes = hex(SPEC_PACKET);
+        let packet = DataPacket::parse(&bytes).unwrap();
+        assert_eq!(packet.source_name, "xLights");
+        assert_eq!(packet.priority, 100);
+        assert_eq!(packet.sequence, 0x2a);
+        assert_eq!(packet.universe, 1);
+        assert_eq!(packet.data, &[255, 0, 0, 0, 255, 0]);
+        assert!(!packet.preview);
+        assert_eq!(packet.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn given_broken_packet_returns_error() {
+        let bytes = hex(SPEC_PACKET);
+        assert_eq!(DataPacket::parse(&bytes[..100]), Err(E131Error::Truncated));
+        assert_eq!(DataPacket::parse(&bytes[..130]), Err(E131Error::Truncated));
+        let mut other = bytes.clone();
+        other[4] = b'X';
+        assert_eq!(DataPacket::parse(&other), Err(E131Error::NotE131));
+        let mut sync = bytes.clone();
+        sync[21] = 0x08;
+        assert_eq!(
+            DataPacket::parse(&sync),
+            Err(E131Error::UnsupportedVector(8))
+        );
+        let mut dmp = bytes;
+        dmp[118] = 0;
+        assert_eq!(DataPacket::parse(&dmp), Err(E131Error::InvalidDmp));
+    }
+
+    #[test]
+    fn assembles_universes() {
+        let mut receiver = Receiver::new(Layout::new(1, 3).with_leds_per_universe(2));
+        let now = Instant::now();
+        assert_eq!(receiver.handle(&packet(2, 0, 100, &[3; 3]), now), Ok(None));
+        assert_eq!(
+            receiver.handle(&packet(1, 0, 100, &[1, 1, 1, 2, 2, 2]), now),
+            Ok(Some(Command::Stream(&[1, 1, 1, 2, 2, 2, 3, 3, 3][..])))
+        );
+        assert_eq!(receiver.handle(&packet(9, 1, 100, &[9; 3]), now), Ok(None));
+    }
+
+    #[test]
+    fn drops_out_of_order_packets() {
+        let mut receiver = Receiver::new(Layout::new(1, 1));
+        let now = Instant::now();
+        assert!(receiver
+            .handle(&packet(1, 255, 100, &[1; 3]), now)
+            .unwrap()
+            .is_some());
+        // Wrapped around
+        assert!(receiver
+            .handle(&packet(1, 0, 100, &[2; 3]), now)
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            receiver.handle(&packet(1, 250, 100, &[3; 3]), now),
+            Ok(None)
+        );
+        assert_eq!(receiver.handle(&packet(1, 0, 100, &[3; 3]), now), Ok(None));
+        assert!(receiver
+            .handle(&packet(1, 200, 100, &[4; 3]), now)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn highest_priority_source_wins() {
+        let mut receiver = Receiver::new(Layout::new(1, 1));
+        let now = Instant::now();
+        let mut high = packet(1, 0, 150, &[1; 3]);
+        high[22] = 1;
+        assert!(receiver.handle(&high, now).unwrap().is_some());
+        assert_eq!(receiver.handle(&packet(1, 0, 100, &[2; 3]), now), Ok(None));
+        let later = now + SOURCE_TIMEOUT + Duration::from_millis(1);
+        assert_eq!(
+            receiver.handle(&packet(1, 1, 100, &[2; 3]), later),
+            Ok(Some(Command::Stream(&[2, 2, 2][..])))
+        );
+    }
+
+    #[test]
+    fn ignores_preview_and_terminated_streams() {
+        let mut receiver = Receiver::new(Layout::new(1, 1));
+        let now = Instant::now();
+        let mut preview = packet(1, 0, 100, &[1; 3]);
+        preview[112] = 0x80;
+        assert_eq!(receiver.handle(&preview, now), Ok(None));
+        let mut high = packet(1, 0, 200, &[1; 3]);
+        high[22] = 1;
+        assert!(receiver.handle(&high, now).unwrap().is_some());
+        high[111] = 1;
+        high[112] = 0x40;
+        assert_eq!(receiver.handle(&high, now), Ok(None));
+        // Terminated source no longer blocks lower priorities
+        assert!(receiver
+            .handle(&packet(1, 0, 100, &[2; 3]), now)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn maps_multicast_addr() {
+        assert_eq!(multicast_addr(1).octets(), [239, 255, 0, 1]);
+        assert_eq!(multicast_addr(0x1234).octets(), [239, 255, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn e2e_localhost() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.connect(socket.local_addr().unwrap()).unwrap();
+        let mut receiver = Receiver::new(Layout::new(1, 2));
+        sender.send(b"garbage").unwrap();
+        sender.send(&hex(SPEC_PACKET)).unwrap();
+        assert_eq!(
+            receiver.recv(&socket).unwrap(),
+            Command::Stream(&[255, 0, 0, 0, 255, 0][..])
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{Command, CommandError};
-use core::convert::TryFrom;
+use core::{convert::TryFrom, iter::FusedIterator};
+
+/// Size of the largest command with no payload (`Pulse`), `Stream` takes 1 byte plus its payload
+pub const MAX_FIXED_SIZE: usize = 12;
 
 fn try_read_colour(slice: &[u8]) -> Result<(u8, u8, u8), CommandError> {
     if slice.len() < 3 {
@@ -82,6 +85,199 @@ where
     }
 }
 
+/// Native encoding split into a header kept on the stack and `Stream` payload borrowed from the command.
+/// Lets scatter/gather or DMA transfers send a command without a buffer as large as the whole of it.
+#[derive(Debug, Clone, Copy)]
+pub struct Encoded<'a> {
+    head: [u8; MAX_FIXED_SIZE],
+    head_len: usize,
+    payload: &'a [u8],
+}
+
+impl<'a> Encoded<'a> {
+    /// Command header, whole command unless it is a `Stream`
+    pub fn head(&self) -> &[u8] {
+        &self.head[..self.head_len]
+    }
+
+    /// `Stream` payload, empty for other commands
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    /// Head and payload, concatenated they equal `try_write_bytes` output
+    pub fn parts(&self) -> [&[u8]; 2] {
+        [self.head(), self.payload]
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.head_len + self.payload.len()
+    }
+
+    /// Iterates over the encoding in slices of at most `max_len` bytes.
+    /// Head and payload are never joined into one chunk.
+    ///
+    /// Panics if `max_len` is 0
+    pub fn chunks(&self, max_len: usize) -> EncodedChunks<'_> {
+        assert!(max_len != 0, "chunk size must be non-zero");
+        EncodedChunks {
+            head: self.head(),
+            payload: self.payload,
+            max_len,
+        }
+    }
+}
+
+/// Iterator returned by [`Encoded::chunks`]
+#[derive(Debug, Clone)]
+pub struct EncodedChunks<'a> {
+    head: &'a [u8],
+    payload: &'a [u8],
+    max_len: usize,
+}
+
+impl<'a> Iterator for EncodedChunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = if self.head.is_empty() {
+            &mut self.payload
+        } else {
+            &mut self.head
+        };
+        if remaining.is_empty() {
+            return None;
+        }
+        let (chunk, rest) = remaining.split_at(self.max_len.min(remaining.len()));
+        *remaining = rest;
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len =
+            self.head.len().div_ceil(self.max_len) + self.payload.len().div_ceil(self.max_len);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for EncodedChunks<'_> {}
+
+impl FusedIterator for EncodedChunks<'_> {}
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Native encoding that borrows `Stream` payload instead of copying it
+    pub fn encoded(&self) -> Encoded<'_> {
+        let mut head = [0_u8; MAX_FIXED_SIZE];
+        match self {
+            Command::Stream(bytes) => {
+                head[0] = b's';
+                Encoded {
+                    head,
+                    head_len: 1,
+                    payload: bytes.as_ref(),
+                }
+            }
+            _ => {
+                // Fixed size commands fit into MAX_FIXED_SIZE bytes
+                let head_len = self.try_write_bytes(&mut head).unwrap_or_default();
+                Encoded {
+                    head,
+                    head_len,
+                    payload: &[],
+                }
+            }
+        }
+    }
+}
+
+impl Command<&[u8]> {
+    /// `const` counterpart of [`Command::size_in_bytes`]
+    pub const fn encoded_len(&self) -> usize {
+        match self {
+            Command::Constant { .. } => 6,
+            Command::Stream(bytes) => bytes.len() + 1,
+            Command::Pulse { .. } => MAX_FIXED_SIZE,
+            Command::Health => 1,
+        }
+    }
+
+    /// `const` counterpart of [`Command::try_write_bytes`], see [`command_bytes!`](crate::command_bytes).
+    ///
+    /// Panics if `N` differs from [`Command::encoded_len`], in a constant that is a compile error
+    pub const fn to_bytes<const N: usize>(&self) -> [u8; N] {
+        assert!(N == self.encoded_len(), "N must equal encoded_len()");
+        let mut buf = [0_u8; N];
+        match self {
+            Command::Health => buf[0] = b'h',
+            Command::Constant { led_count, colour } => {
+                let led_count = led_count.to_be_bytes();
+                buf[0] = b'c';
+                buf[1] = led_count[0];
+                buf[2] = led_count[1];
+                buf[3] = colour.0;
+                buf[4] = colour.1;
+                buf[5] = colour.2;
+            }
+            Command::Stream(bytes) => {
+                buf[0] = b's';
+                let mut idx = 0;
+                while idx < bytes.len() {
+                    buf[idx + 1] = bytes[idx];
+                    idx += 1;
+                }
+            }
+            Command::Pulse {
+                led_count,
+                start,
+                end,
+                frames,
+                period,
+            } => {
+                let led_count = led_count.to_be_bytes();
+                let period = period.to_be_bytes();
+                buf[0] = b'p';
+                buf[1] = led_count[0];
+                buf[2] = led_count[1];
+                buf[3] = start.0;
+                buf[4] = start.1;
+                buf[5] = start.2;
+                buf[6] = end.0;
+                buf[7] = end.1;
+                buf[8] = end.2;
+                buf[9] = *frames;
+                buf[10] = period[0];
+                buf[11] = period[1];
+            }
+        }
+        buf
+    }
+}
+
+/// Native encoding of a command as a `[u8; N]` constant, so encoded scenes can live in flash.
+///
+/// Takes a `Command` variant without the `Command::` prefix:
+///
+/// ```
+/// use command::command_bytes;
+///
+/// static BOOT: [u8; 6] = command_bytes!(Constant {
+///     led_count: 60,
+///     colour: (255, 0, 0)
+/// });
+/// assert_eq!(BOOT, [b'c', 0, 60, 255, 0, 0]);
+/// ```
+#[macro_export]
+macro_rules! command_bytes {
+    ($($command:tt)+) => {{
+        const COMMAND: $crate::Command<&'static [u8]> = $crate::Command::$($command)+;
+        const BYTES: [u8; COMMAND.encoded_len()] = COMMAND.to_bytes();
+        BYTES
+    }};
+}
+
 impl<'a> TryFrom<&'a [u8]> for Command<&'a [u8]> {
     type Error = CommandError;
 
@@ -100,11 +296,17 @@ impl<'a> TryFrom<&'a [u8]> for Command<&'a [u8]> {
                 }
             }
             b'c' => {
+                if buffer.len() < 5 {
+                    return Err(CommandError::MalformedPayload);
+                }
                 let led_count = try_read_u16(&buffer[..2])?;
                 let colour = try_read_colour(&buffer[2..])?;
                 Ok(Command::Constant { led_count, colour })
             }
             b'p' => {
+                if buffer.len() < 11 {
+                    return Err(CommandError::MalformedPayload);
+                }
                 let led_count = try_read_u16(&buffer[..2])?;
                 let start_led = try_read_colour(&buffer[2..5])?;
                 let end_led = try_read_colour(&buffer[5..8])?;
@@ -131,7 +333,7 @@ mod tests {
 
     use crate::{
         embedded::{try_read_colour, try_read_u16, try_write_colour, try_write_u16},
-        Command, CommandError,
+        Command, CommandError, MAX_FIXED_SIZE,
     };
 
     #[test]
@@ -254,4 +456,156 @@ mod tests {
         assert!(deserialized.is_ok());
         assert_eq!(deserialized.unwrap(), cmd);
     }
+
+    #[test]
+    fn given_truncated_payload_returns_error() {
+        for truncated in [
+            &b"c"[..],
+            &b"c\x01\x01\x00"[..],
+            &b"p\x01\x2c\x00\x00\x00"[..],
+        ] {
+            let outcome = Command::<&[u8]>::try_from(truncated);
+            assert_eq!(outcome.unwrap_err(), CommandError::MalformedPayload);
+        }
+    }
+
+    #[test]
+    fn given_empty_slice_returns_error() {
+        let outcome = Command::<&[u8]>::try_from(&[][..]);
+        assert_eq!(outcome.unwrap_err(), CommandError::MalformedPayload);
+    }
+
+    #[test]
+    fn given_unknown_header_returns_error() {
+        let outcome = Command::<&[u8]>::try_from(&b"x"[..]);
+        assert_eq!(outcome.unwrap_err(), CommandError::InvalidHeader);
+    }
+
+    fn encoded_cases() -> [Command<&'static [u8]>; 4] {
+        [
+            Command::Health,
+            Command::Constant {
+                led_count: 257,
+                colour: (254, 0, 254),
+            },
+            Command::Stream(&[127, 127, 127, 0, 0, 0, 127, 127, 127, 0, 0, 0]),
+            Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            },
+        ]
+    }
+
+    #[test]
+    fn encoded_chunks_match_try_write_bytes() {
+        for cmd in encoded_cases().iter() {
+            let mut expected = [0_u8; 128];
+            let len = cmd.try_write_bytes(&mut expected).unwrap();
+            let encoded = cmd.encoded();
+            assert_eq!(encoded.size_in_bytes(), len);
+            for max_len in 1..=16 {
+                let mut joined = [0_u8; 128];
+                let mut written = 0;
+                let chunks = encoded.chunks(max_len);
+                let count = chunks.len();
+                for chunk in chunks {
+                    assert!(chunk.len() <= max_len);
+                    joined[written..written + chunk.len()].copy_from_slice(chunk);
+                    written += chunk.len();
+                }
+                assert_eq!(&joined[..written], &expected[..len]);
+                assert_eq!(count, encoded.chunks(max_len).count());
+            }
+        }
+    }
+
+    #[test]
+    fn encoded_stream_borrows_payload() {
+        let payload: &[u8] = &[1, 2, 3, 4, 5, 6];
+        let cmd = Command::Stream(payload);
+        let encoded = cmd.encoded();
+        assert_eq!(encoded.parts(), [&b"s"[..], payload]);
+        let mut chunks = encoded.chunks(4);
+        assert_eq!(chunks.next(), Some(&b"s"[..]));
+        let chunk = chunks.next().unwrap();
+        assert_eq!(chunk.as_ptr(), payload.as_ptr());
+        assert_eq!(chunk.len(), 4);
+        assert_eq!(chunks.next().unwrap().as_ptr(), payload[4..].as_ptr());
+        assert_eq!(chunks.next(), None);
+    }
+
+    #[test]
+    fn encoded_fixed_command_has_no_payload() {
+        let cmd: Command<&[u8]> = Command::Constant {
+            led_count: 257,
+            colour: (254, 0, 254),
+        };
+        let encoded = cmd.encoded();
+        assert_eq!(encoded.head(), &[b'c', 1, 1, 254, 0, 254]);
+        assert!(encoded.payload().is_empty());
+    }
+
+    #[test]
+    fn const_encoding_matches_try_write_bytes() {
+        const COMMANDS: [Command<&[u8]>; 4] = [
+            Command::Health,
+            Command::Constant {
+                led_count: 257,
+                colour: (254, 0, 254),
+            },
+            Command::Stream(&[1, 2, 3]),
+            Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            },
+        ];
+        const PULSE: [u8; MAX_FIXED_SIZE] = COMMANDS[3].to_bytes();
+        let mut buf = [0_u8; MAX_FIXED_SIZE];
+        for command in COMMANDS.iter() {
+            let len = command.try_write_bytes(&mut buf).unwrap();
+            assert_eq!(command.encoded_len(), len);
+            assert!(len <= MAX_FIXED_SIZE);
+        }
+        COMMANDS[3].try_write_bytes(&mut buf).unwrap();
+        assert_eq!(PULSE, buf);
+    }
+
+    #[test]
+    fn builds_command_tables() {
+        static SCENE: [&[u8]; 3] = [
+            &crate::command_bytes!(Health),
+            &crate::command_bytes!(Stream(&[1, 2, 3])),
+            &crate::command_bytes!(Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            }),
+        ];
+        assert_eq!(SCENE[0], b"h");
+        assert_eq!(SCENE[1], &[b's', 1, 2, 3]);
+        assert_eq!(
+            Command::<&[u8]>::try_from(SCENE[2]),
+            Ok(Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "N must equal encoded_len()")]
+    fn given_wrong_len_to_bytes_panics() {
+        let _: [u8; 2] = Command::<&[u8]>::Health.to_bytes();
+    }
 }
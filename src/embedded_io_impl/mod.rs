@@ -0,0 +1,317 @@
+#![cfg(feature = "embedded_io_impl")]
+//! Commands over `embedded-io` byte streams.
+//!
+//! Frames are the big endian u16 length of the native encoding followed by the encoding itself,
+//! same as `CommandCodec`, so a host using the codec and firmware using these helpers understand each other.
+use crate::{
+    framing::{frame_len, frame_prefix, Skip, FRAME_PREFIX_LEN},
+    Command, CommandError,
+};
+use core::convert::TryFrom;
+use embedded_io::{Read, ReadExactError, Write};
+
+#[derive(Debug, PartialEq)]
+pub enum IoError<E> {
+    Io(E),
+    /// Reader ran out of data in the middle of a frame
+    UnexpectedEof,
+    /// Encoded command does not fit into the length prefix
+    FrameTooLarge(usize),
+    Command(CommandError),
+}
+
+impl<E> From<ReadExactError<E>> for IoError<E> {
+    fn from(value: ReadExactError<E>) -> Self {
+        match value {
+            ReadExactError::UnexpectedEof => IoError::UnexpectedEof,
+            ReadExactError::Other(err) => IoError::Io(err),
+        }
+    }
+}
+
+impl<E> From<CommandError> for IoError<E> {
+    fn from(value: CommandError) -> Self {
+        IoError::Command(value)
+    }
+}
+
+fn command_prefix<T, E>(command: &Command<T>) -> Result<[u8; FRAME_PREFIX_LEN], IoError<E>>
+where
+    T: AsRef<[u8]>,
+{
+    let len = command.size_in_bytes();
+    frame_prefix(len).ok_or(IoError::FrameTooLarge(len))
+}
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Writes length prefixed command, `Stream` payload goes to `writer` without being copied.
+    /// Returns number of bytes written
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<usize, IoError<W::Error>> {
+        writer
+            .write_all(&command_prefix(self)?)
+            .map_err(IoError::Io)?;
+        let encoded = self.encoded();
+        for part in encoded.parts().iter() {
+            writer.write_all(part).map_err(IoError::Io)?;
+        }
+        Ok(FRAME_PREFIX_LEN + encoded.size_in_bytes())
+    }
+
+    #[cfg(feature = "embedded_io_async_impl")]
+    /// Async counterpart of [`Command::write_to`]
+    pub async fn write_to_async<W: embedded_io_async::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<usize, IoError<W::Error>> {
+        writer
+            .write_all(&command_prefix(self)?)
+            .await
+            .map_err(IoError::Io)?;
+        let encoded = self.encoded();
+        for part in encoded.parts().iter() {
+            writer.write_all(part).await.map_err(IoError::Io)?;
+        }
+        Ok(FRAME_PREFIX_LEN + encoded.size_in_bytes())
+    }
+}
+
+/// Reads one length prefixed command into `buf`, `Stream` payload borrows from it.
+///
+/// Frame larger than `buf` is skipped and reported as `CommandError::BufferTooSmall`,
+/// so the next call starts at the following frame.
+pub fn read_command<'a, R: Read>(
+    reader: &mut R,
+    buf: &'a mut [u8],
+) -> Result<Command<&'a [u8]>, IoError<R::Error>> {
+    let mut prefix = [0_u8; FRAME_PREFIX_LEN];
+    reader.read_exact(&mut prefix)?;
+    let len = frame_len(prefix);
+    if len > buf.len() {
+        let mut skip = Skip::new(len);
+        while let Some(chunk) = skip.next(buf) {
+            reader.read_exact(chunk)?;
+        }
+        return Err(CommandError::BufferTooSmall.into());
+    }
+
+    reader.read_exact(&mut buf[..len])?;
+    Ok(Command::try_from(&buf[..len])?)
+}
+
+#[cfg(feature = "embedded_io_async_impl")]
+/// Async counterpart of [`read_command`]
+pub async fn read_command_async<'a, R: embedded_io_async::Read>(
+    reader: &mut R,
+    buf: &'a mut [u8],
+) -> Result<Command<&'a [u8]>, IoError<R::Error>> {
+    let mut prefix = [0_u8; FRAME_PREFIX_LEN];
+    reader.read_exact(&mut prefix).await?;
+    let len = frame_len(prefix);
+    if len > buf.len() {
+        let mut skip = Skip::new(len);
+        while let Some(chunk) = skip.next(buf) {
+            reader.read_exact(chunk).await?;
+        }
+        return Err(CommandError::BufferTooSmall.into());
+    }
+
+    reader.read_exact(&mut buf[..len]).await?;
+    Ok(Command::try_from(&buf[..len])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_command, IoError};
+    use crate::{Command, CommandError};
+    use core::convert::Infallible;
+    use embedded_io::{ErrorType, Read, Write};
+    extern crate std;
+    use std::{vec, vec::Vec};
+
+    /// Accepts at most `chunk` bytes per call and remembers how many calls it took
+    struct MockWriter {
+        data: Vec<u8>,
+        chunk: usize,
+        calls: usize,
+    }
+
+    impl ErrorType for MockWriter {
+        type Error = Infallible;
+    }
+
+    impl Write for MockWriter {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            let len = buf.len().min(self.chunk);
+            self.data.extend_from_slice(&buf[..len]);
+            self.calls += 1;
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "embedded_io_async_impl")]
+    impl embedded_io_async::Write for MockWriter {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Write::write(self, buf)
+        }
+    }
+
+    /// Hands out at most `chunk` bytes per call
+    struct MockReader<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl ErrorType for MockReader<'_> {
+        type Error = Infallible;
+    }
+
+    impl Read for MockReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let len = buf.len().min(self.chunk).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[cfg(feature = "embedded_io_async_impl")]
+    impl embedded_io_async::Read for MockReader<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Read::read(self, buf)
+        }
+    }
+
+    fn writer(chunk: usize) -> MockWriter {
+        MockWriter {
+            data: Vec::new(),
+            chunk,
+            calls: 0,
+        }
+    }
+
+    fn commands() -> Vec<Command<&'static [u8]>> {
+        vec![
+            Command::Health,
+            Command::Constant {
+                led_count: 257,
+                colour: (254, 0, 254),
+            },
+            Command::Stream(&[127; 30]),
+            Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            },
+        ]
+    }
+
+    #[test]
+    fn writes_stream_payload_in_place() {
+        let mut writer = writer(usize::MAX);
+        let written = Command::Stream([1_u8, 2, 3].as_ref())
+            .write_to(&mut writer)
+            .unwrap();
+        assert_eq!(written, 6);
+        assert_eq!(writer.data, vec![0, 4, b's', 1, 2, 3]);
+        // Length prefix, header and payload, no copy into a scratch buffer
+        assert_eq!(writer.calls, 3);
+    }
+
+    #[test]
+    fn e2e_chunked() {
+        let mut writer = writer(5);
+        for command in commands() {
+            command.write_to(&mut writer).unwrap();
+        }
+        let mut reader = MockReader {
+            data: &writer.data,
+            chunk: 3,
+        };
+        let mut buf = [0_u8; 64];
+        for command in commands() {
+            assert_eq!(read_command(&mut reader, &mut buf).unwrap(), command);
+        }
+        assert_eq!(
+            read_command(&mut reader, &mut buf).unwrap_err(),
+            IoError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn given_frame_larger_than_buf_skips_it() {
+        let mut writer = writer(usize::MAX);
+        Command::Stream([0_u8; 30].as_ref())
+            .write_to(&mut writer)
+            .unwrap();
+        Command::<&[u8]>::Health.write_to(&mut writer).unwrap();
+        let mut reader = MockReader {
+            data: &writer.data,
+            chunk: usize::MAX,
+        };
+        let mut buf = [0_u8; 8];
+        assert_eq!(
+            read_command(&mut reader, &mut buf).unwrap_err(),
+            IoError::Command(CommandError::BufferTooSmall)
+        );
+        assert_eq!(
+            read_command(&mut reader, &mut buf).unwrap(),
+            Command::Health
+        );
+    }
+
+    #[test]
+    fn given_truncated_frame_returns_eof() {
+        let mut reader = MockReader {
+            data: &[0, 6, b'c', 1],
+            chunk: usize::MAX,
+        };
+        let mut buf = [0_u8; 8];
+        assert_eq!(
+            read_command(&mut reader, &mut buf).unwrap_err(),
+            IoError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn given_garbled_frame_returns_error() {
+        let mut reader = MockReader {
+            data: &[0, 1, b'x'],
+            chunk: usize::MAX,
+        };
+        let mut buf = [0_u8; 8];
+        assert_eq!(
+            read_command(&mut reader, &mut buf).unwrap_err(),
+            IoError::Command(CommandError::InvalidHeader)
+        );
+    }
+
+    #[cfg(feature = "embedded_io_async_impl")]
+    #[test]
+    fn e2e_async() {
+        use super::read_command_async;
+        futures::executor::block_on(async {
+            let mut writer = writer(5);
+            for command in commands() {
+                command.write_to_async(&mut writer).await.unwrap();
+            }
+            let mut reader = MockReader {
+                data: &writer.data,
+                chunk: 3,
+            };
+            let mut buf = [0_u8; 64];
+            for command in commands() {
+                let read = read_command_async(&mut reader, &mut buf).await.unwrap();
+                assert_eq!(read, command);
+            }
+        });
+    }
+}
@@ -9,48 +9,177 @@ impl Format for CommandError {
             CommandError::InvalidHeader => defmt::write!(f, "CH"),
             CommandError::MalformedPayload => defmt::write!(f, "CP"),
             CommandError::BufferTooSmall => defmt::write!(f, "CS"),
+            CommandError::ValueOutOfRange => defmt::write!(f, "CV"),
+            CommandError::MissingField(field) => defmt::write!(f, "CM({=str})", field),
         }
     }
 }
 
-impl<'a> Format for Command<&'a [u8]> {
+impl<T: AsRef<[u8]>> Format for Command<T> {
     fn format(&self, f: defmt::Formatter) {
         match self {
             Command::Constant { led_count, colour } => defmt::write!(
                 f,
-                "CC::L({})::CO({},{},{})",
+                "CC::L({=u16})::CO({=u8},{=u8},{=u8})",
                 led_count,
                 colour.0,
                 colour.1,
                 colour.2
             ),
-            Command::Stream(bytes) => defmt::write!(f, "CS::LB({})", bytes.len()),
-            Command::Pulse { led_count, .. } => defmt::write!(f, "CP::L({}))", led_count),
+            Command::Stream(bytes) => defmt::write!(f, "CS::LB({=usize})", bytes.as_ref().len()),
+            Command::Pulse {
+                led_count,
+                start,
+                end,
+                frames,
+                period,
+            } => defmt::write!(
+                f,
+                "CP::L({=u16})::S({=u8},{=u8},{=u8})::E({=u8},{=u8},{=u8})::F({=u8})::P({=u16})",
+                led_count,
+                start.0,
+                start.1,
+                start.2,
+                end.0,
+                end.1,
+                end.2,
+                frames,
+                period
+            ),
             Command::Health => defmt::write!(f, "CH"),
         }
     }
 }
 
-#[cfg(feature = "owned")]
-extern crate std;
-#[cfg(feature = "owned")]
-use std::vec::Vec;
+/// Logs `Stream` together with its leading payload bytes in hex, see [`Command::preview`]
+pub struct Preview<'a, T> {
+    command: &'a Command<T>,
+    len: usize,
+}
+
+impl<T: AsRef<[u8]>> Command<T> {
+    /// Formats like the plain `Format` impl, but `Stream` also logs up to `len` payload bytes
+    pub fn preview(&self, len: usize) -> Preview<'_, T> {
+        Preview { command: self, len }
+    }
+}
+
+impl<T: AsRef<[u8]>> Format for Preview<'_, T> {
+    fn format(&self, f: defmt::Formatter) {
+        match self.command {
+            Command::Stream(bytes) => {
+                let bytes = bytes.as_ref();
+                defmt::write!(
+                    f,
+                    "CS::LB({=usize})::B({=[u8]:x})",
+                    bytes.len(),
+                    &bytes[..bytes.len().min(self.len)]
+                )
+            }
+            command => command.format(f),
+        }
+    }
+}
 
-#[cfg(feature = "owned")]
-impl Format for Command<Vec<u8>> {
+#[cfg(feature = "embedded_io_impl")]
+impl<E: Format> Format for crate::IoError<E> {
     fn format(&self, f: defmt::Formatter) {
         match self {
-            Command::Constant { led_count, colour } => defmt::write!(
-                f,
-                "CC::L({})::CO({},{},{})",
-                led_count,
-                colour.0,
-                colour.1,
-                colour.2
-            ),
-            Command::Stream(bytes) => defmt::write!(f, "CS::LB({})", bytes.len()),
-            Command::Pulse { led_count, .. } => defmt::write!(f, "CP::L({}))", led_count),
-            Command::Health => defmt::write!(f, "CH"),
+            crate::IoError::Io(err) => defmt::write!(f, "IO({})", err),
+            crate::IoError::UnexpectedEof => defmt::write!(f, "IE"),
+            crate::IoError::FrameTooLarge(len) => defmt::write!(f, "IF({=usize})", len),
+            crate::IoError::Command(err) => defmt::write!(f, "IC({})", err),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Command, CommandError};
+    use defmt::{export, Format};
+    extern crate std;
+    use std::vec::Vec;
+
+    /// Formats `value` with defmt's host test encoder, returns the interned string index
+    /// the value started with and the logged bytes
+    fn log(value: &impl Format) -> (u16, Vec<u8>) {
+        let index = export::fetch_string_index();
+        value.format(export::make_formatter());
+        (index, export::fetch_bytes())
+    }
+
+    fn frame(index: u16, args: &[u8]) -> Vec<u8> {
+        let mut frame = index.to_le_bytes().to_vec();
+        frame.extend_from_slice(args);
+        frame
+    }
+
+    #[test]
+    fn logs_every_pulse_field() {
+        let cmd = Command::<&[u8]>::Pulse {
+            led_count: 300,
+            start: (1, 2, 3),
+            end: (4, 5, 6),
+            frames: 60,
+            period: 1000,
+        };
+        let (index, bytes) = log(&cmd);
+        assert_eq!(bytes, frame(index, &[44, 1, 1, 2, 3, 4, 5, 6, 60, 232, 3]));
+    }
+
+    #[test]
+    fn logs_any_storage() {
+        let slice = Command::Stream(&[0_u8; 6][..]);
+        let array = Command::Stream([0_u8; 6]);
+        let (_, from_slice) = log(&slice);
+        let (_, from_array) = log(&array);
+        assert_eq!(from_slice[2..], from_array[2..]);
+        assert_eq!(from_slice[2..], [6, 0, 0, 0]);
+    }
+
+    #[test]
+    fn previews_stream_bytes() {
+        let cmd = Command::Stream(&[1_u8, 2, 3, 4, 5, 6][..]);
+        let (index, bytes) = log(&cmd.preview(4));
+        assert_eq!(bytes, frame(index, &[6, 0, 0, 0, 4, 0, 0, 0, 1, 2, 3, 4]));
+        let (_, bytes) = log(&cmd.preview(16));
+        assert_eq!(bytes[6..10], [6, 0, 0, 0]);
+    }
+
+    #[test]
+    fn preview_of_other_commands_matches_format() {
+        let cmd = Command::<&[u8]>::Constant {
+            led_count: 1,
+            colour: (2, 3, 4),
+        };
+        let (_, plain) = log(&cmd);
+        let (_, preview) = log(&cmd.preview(4));
+        assert_eq!(plain[2..], preview[2..]);
+        assert_eq!(plain[2..], [1, 0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn logs_errors() {
+        let (index, bytes) = log(&CommandError::ValueOutOfRange);
+        assert_eq!(bytes, frame(index, &[]));
+        assert_eq!(export::fetch_string_index(), index + 1);
+        let (_, bytes) = log(&CommandError::MissingField("colour"));
+        assert!(bytes.ends_with(b"colour"));
+    }
+
+    #[cfg(feature = "embedded_io_impl")]
+    #[test]
+    fn logs_io_errors() {
+        let err = crate::IoError::<CommandError>::FrameTooLarge(70_000);
+        let (index, bytes) = log(&err);
+        assert_eq!(bytes, frame(index, &[112, 17, 1, 0]));
+        let err = crate::IoError::<CommandError>::Command(CommandError::BufferTooSmall);
+        let (index, bytes) = log(&err);
+        // Nested error is a format sequence: its tag, its own interned string and a terminating 0
+        let indices: Vec<u16> = bytes
+            .chunks(2)
+            .map(|index| u16::from_le_bytes([index[0], index[1]]))
+            .collect();
+        assert_eq!(indices, [index, index + 1, index + 2, 0]);
+    }
+}
@@ -7,16 +7,24 @@ pub use defmt_impl::*;
 #[cfg(all(feature = "owned", feature = "stderr"))]
 mod owned;
 
+// Only holds the `std::error::Error` impl, the glob keeps anything it makes public next to `CommandError`
 #[cfg(all(feature = "owned", feature = "stderr"))]
+#[allow(unused_imports)]
 pub use owned::*;
 
 use core::fmt::{Display, Formatter, Result as FMTResult};
 
+/// New variants may be added in minor releases, matches need a wildcard arm
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum CommandError {
     InvalidHeader,
     MalformedPayload,
     BufferTooSmall,
+    /// Value does not fit into its field, e.g. more than `u16::MAX` LEDs
+    ValueOutOfRange,
+    /// Builder field that has to be set was not, e.g. `colour` of a `Constant`
+    MissingField(&'static str),
 }
 
 impl From<CommandError> for &'static str {
@@ -31,6 +39,8 @@ impl From<&CommandError> for &'static str {
             CommandError::BufferTooSmall => "CS",
             CommandError::InvalidHeader => "CH",
             CommandError::MalformedPayload => "CP",
+            CommandError::ValueOutOfRange => "CV",
+            CommandError::MissingField(_) => "CM",
         }
     }
 }
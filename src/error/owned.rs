@@ -1,5 +1,7 @@
 #![cfg(all(feature = "owned", feature = "stderr"))]
 
+extern crate std;
+
 use crate::CommandError;
 
 impl std::error::Error for CommandError {
@@ -0,0 +1,37 @@
+//! Frames of protocols that carry the colour of every LED at once, e.g. Adalight, TPM2 and OPC.
+use crate::{Command, CommandError};
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Writes colours of every LED into `buf` for protocols that only carry whole frames,
+    /// `Constant` is spelled out LED by LED. Returns their length.
+    ///
+    /// Fails with `InvalidHeader` for commands without colours (`Health`, `Pulse`),
+    /// `MalformedPayload` when `Stream` payload is not whole pixels and `BufferTooSmall`
+    /// when the frame does not fit into `buf`
+    pub(crate) fn write_frame(&self, buf: &mut [u8]) -> Result<usize, CommandError> {
+        match self {
+            Command::Stream(bytes) => {
+                let bytes = bytes.as_ref();
+                if bytes.len() % 3 != 0 {
+                    return Err(CommandError::MalformedPayload);
+                }
+                buf.get_mut(..bytes.len())
+                    .ok_or(CommandError::BufferTooSmall)?
+                    .copy_from_slice(bytes);
+                Ok(bytes.len())
+            }
+            Command::Constant { led_count, colour } => {
+                let len = *led_count as usize * 3;
+                let frame = buf.get_mut(..len).ok_or(CommandError::BufferTooSmall)?;
+                for rgb in frame.chunks_exact_mut(3) {
+                    rgb.copy_from_slice(&[colour.0, colour.1, colour.2]);
+                }
+                Ok(len)
+            }
+            _ => Err(CommandError::InvalidHeader),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Big endian u16 length prefix that lets native commands share a byte stream,
+//! the encoding has no length of its own (`Stream` payload runs to the end of the buffer).
+//! Used by `CommandCodec`, the `embedded-io` helpers and `command-cli`.
+use core::convert::TryFrom;
+
+pub(crate) const FRAME_PREFIX_LEN: usize = 2;
+
+/// Prefix of a frame holding `len` bytes, `None` when `len` does not fit into it
+pub(crate) fn frame_prefix(len: usize) -> Option<[u8; FRAME_PREFIX_LEN]> {
+    u16::try_from(len).ok().map(u16::to_be_bytes)
+}
+
+/// Length of the frame following `prefix`
+pub(crate) fn frame_len(prefix: [u8; FRAME_PREFIX_LEN]) -> usize {
+    u16::from_be_bytes(prefix) as usize
+}
+
+/// Reads past a frame too large for the reader's buffer, using that buffer for the discarded bytes
+#[cfg(feature = "embedded_io_impl")]
+pub(crate) struct Skip {
+    remaining: usize,
+    scratch: [u8; 1],
+}
+
+#[cfg(feature = "embedded_io_impl")]
+impl Skip {
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            remaining: len,
+            scratch: [0],
+        }
+    }
+
+    /// Part of `buf` to read the next bytes of the frame into, `None` once all of it was read.
+    /// Empty `buf` is stood in for by a single byte
+    pub(crate) fn next<'a>(&'a mut self, buf: &'a mut [u8]) -> Option<&'a mut [u8]> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let chunk = if buf.is_empty() {
+            &mut self.scratch[..]
+        } else {
+            let len = self.remaining.min(buf.len());
+            &mut buf[..len]
+        };
+        self.remaining -= chunk.len();
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_len, frame_prefix};
+
+    #[test]
+    fn round_trips_prefix() {
+        assert_eq!(frame_prefix(300), Some([1, 44]));
+        assert_eq!(frame_len([1, 44]), 300);
+        assert_eq!(frame_prefix(u16::MAX as usize), Some([255, 255]));
+        assert_eq!(frame_prefix(u16::MAX as usize + 1), None);
+    }
+
+    #[cfg(feature = "embedded_io_impl")]
+    #[test]
+    fn skips_in_chunks_of_buffer() {
+        use super::Skip;
+        let mut buf = [0_u8; 4];
+        let mut skip = Skip::new(10);
+        let mut chunks = [0_usize; 4];
+        let mut count = 0;
+        while let Some(chunk) = skip.next(&mut buf) {
+            chunks[count] = chunk.len();
+            count += 1;
+        }
+        assert_eq!(chunks[..count], [4, 4, 2]);
+        let mut skip = Skip::new(2);
+        assert_eq!(skip.next(&mut []).map(|chunk| chunk.len()), Some(1));
+        assert_eq!(skip.next(&mut []).map(|chunk| chunk.len()), Some(1));
+        assert!(skip.next(&mut []).is_none());
+    }
+}
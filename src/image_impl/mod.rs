@@ -0,0 +1,392 @@
+#![cfg(feature = "image_impl")]
+//! Converts images and animations into `Stream` frames.
+//!
+//! Frames come stamped with milliseconds since the start of the animation,
+//! ready for [`Timeline`](crate::Timeline) or [`RecordingWriter`](crate::recording::RecordingWriter).
+extern crate std;
+use crate::Command;
+use image::{
+    codecs::gif::GifDecoder,
+    error::{ImageError, ParameterError, ParameterErrorKind},
+    imageops::{self, FilterType},
+    AnimationDecoder, DynamicImage, ImageFormat, ImageResult, RgbImage,
+};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek},
+    path::Path,
+    string::ToString,
+    vec::Vec,
+};
+
+/// Frame rate of sprite sheets when none is given
+pub const DEFAULT_FPS: f32 = 30.0;
+/// Highest frame rate [`ImageOptions::validate`] accepts, timestamps are whole milliseconds
+pub const MAX_FPS: f32 = 1000.0;
+/// Most frames resampling an animation may produce
+pub const MAX_FRAMES: usize = 100_000;
+
+/// Which pixels light which LEDs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mapping {
+    /// Every image row is a frame and LED `i` shows column `i`, e.g. a PNG sprite sheet.
+    /// Rows of animation frames follow each other.
+    Rows,
+    /// Every image is a frame and LED `i` shows the `i`-th pixel, those outside the image are off
+    Points(Vec<(u32, u32)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageOptions {
+    pub mapping: Mapping,
+    /// Scales images to this width first, keeping their height, e.g. to the LED count for `Mapping::Rows`
+    pub width: Option<u32>,
+    /// Exponent applied to every channel, 1.0 keeps colours as they are
+    pub gamma: f32,
+    /// Frame rate of the output. Animations mapped with `Mapping::Points` keep their own frame delays
+    /// unless it is set, everything else defaults to [`DEFAULT_FPS`].
+    pub fps: Option<f32>,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            mapping: Mapping::Rows,
+            width: None,
+            gamma: 1.0,
+            fps: None,
+        }
+    }
+}
+
+fn invalid(message: &str) -> ImageError {
+    ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(
+        message.to_string(),
+    )))
+}
+
+impl ImageOptions {
+    /// Fails with `ImageError::Parameter` unless `gamma` is finite and above 0
+    /// and `fps` is above 0 and at most [`MAX_FPS`]
+    pub fn validate(&self) -> ImageResult<()> {
+        if !(self.gamma.is_finite() && self.gamma > 0.0) {
+            return Err(invalid("gamma must be a finite number above 0"));
+        }
+        match self.fps {
+            Some(fps) if !(fps > 0.0 && fps <= MAX_FPS) => {
+                Err(invalid("fps must be above 0 and at most 1000"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Timestamped `Stream` payloads
+type Frames = Vec<(u32, Vec<u8>)>;
+
+fn gamma_table(gamma: f32) -> [u8; 256] {
+    let mut table = [0_u8; 256];
+    for (value, corrected) in table.iter_mut().enumerate() {
+        *corrected = (255.0 * (value as f32 / 255.0).powf(gamma)).round() as u8;
+    }
+    table
+}
+
+fn frame_ms(index: usize, fps: f32) -> u32 {
+    (index as f64 * 1000.0 / fps as f64).round() as u32
+}
+
+/// Payloads of one image, without timing
+fn payloads(image: &RgbImage, options: &ImageOptions, gamma: &[u8; 256]) -> Vec<Vec<u8>> {
+    let resized;
+    let image = match options.width {
+        Some(width) if width != image.width() => {
+            resized = imageops::resize(image, width, image.height(), FilterType::Triangle);
+            &resized
+        }
+        _ => image,
+    };
+    let correct =
+        |channels: &[u8]| -> Vec<u8> { channels.iter().map(|v| gamma[*v as usize]).collect() };
+    match &options.mapping {
+        Mapping::Rows => image
+            .rows()
+            .map(|row| correct(&row.flat_map(|pixel| pixel.0).collect::<Vec<_>>()))
+            .collect(),
+        Mapping::Points(points) => {
+            let bytes: Vec<u8> = points
+                .iter()
+                .flat_map(|(x, y)| {
+                    image
+                        .get_pixel_checked(*x, *y)
+                        .map_or([0; 3], |pixel| pixel.0)
+                })
+                .collect();
+            std::vec![correct(&bytes)]
+        }
+    }
+}
+
+/// Picks the frame shown at every tick of `fps` until `duration` ms,
+/// fails with `ImageError::Parameter` past [`MAX_FRAMES`]
+fn resample(frames: &Frames, duration: u32, fps: f32) -> ImageResult<Frames> {
+    let count = (duration.max(1) as f64 * fps as f64 / 1000.0).ceil();
+    if count > MAX_FRAMES as f64 {
+        return Err(invalid("resampled animation exceeds 100000 frames"));
+    }
+    let mut resampled = Vec::with_capacity(count as usize);
+    let mut current = 0;
+    for index in 0..count as usize {
+        let at = frame_ms(index, fps);
+        while current + 1 < frames.len() && frames[current + 1].0 <= at {
+            current += 1;
+        }
+        resampled.push((at, frames[current].1.clone()));
+    }
+    Ok(resampled)
+}
+
+fn into_commands(frames: Frames) -> Vec<(u32, Command<Vec<u8>>)> {
+    frames
+        .into_iter()
+        .map(|(at, payload)| (at, Command::Stream(payload)))
+        .collect()
+}
+
+/// Converts animation frames given with their delays in ms
+fn convert_frames(
+    images: Vec<(u32, RgbImage)>,
+    options: &ImageOptions,
+) -> ImageResult<Vec<(u32, Command<Vec<u8>>)>> {
+    let gamma = gamma_table(options.gamma);
+    if options.mapping == Mapping::Rows {
+        let fps = options.fps.unwrap_or(DEFAULT_FPS);
+        let frames = images
+            .iter()
+            .flat_map(|(_, image)| payloads(image, options, &gamma))
+            .enumerate()
+            .map(|(index, payload)| (frame_ms(index, fps), payload))
+            .collect();
+        return Ok(into_commands(frames));
+    }
+
+    let mut frames = Vec::with_capacity(images.len());
+    let mut at = 0_u32;
+    for (delay, image) in images.iter() {
+        for payload in payloads(image, options, &gamma) {
+            frames.push((at, payload));
+        }
+        at = at.saturating_add(*delay);
+    }
+    match options.fps {
+        Some(fps) if !frames.is_empty() => Ok(into_commands(resample(&frames, at, fps)?)),
+        _ => Ok(into_commands(frames)),
+    }
+}
+
+/// Converts a still image, e.g. a sprite sheet. Fails on options [`ImageOptions::validate`] rejects
+pub fn convert_image(
+    image: &DynamicImage,
+    options: &ImageOptions,
+) -> ImageResult<Vec<(u32, Command<Vec<u8>>)>> {
+    options.validate()?;
+    convert_frames(std::vec![(0, image.to_rgb8())], options)
+}
+
+/// Converts an animated GIF, frames are composited the way a browser shows them
+pub fn convert_gif<R: BufRead + Seek>(
+    reader: R,
+    options: &ImageOptions,
+) -> ImageResult<Vec<(u32, Command<Vec<u8>>)>> {
+    options.validate()?;
+    let images = GifDecoder::new(reader)?
+        .into_frames()
+        .map(|frame| {
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = numer.checked_div(denom).unwrap_or_default();
+            Ok((
+                delay,
+                DynamicImage::ImageRgba8(frame.into_buffer()).to_rgb8(),
+            ))
+        })
+        .collect::<ImageResult<_>>()?;
+    convert_frames(images, options)
+}
+
+/// Converts a GIF animation or any still image `image` can decode
+pub fn convert_file<P: AsRef<Path>>(
+    path: P,
+    options: &ImageOptions,
+) -> ImageResult<Vec<(u32, Command<Vec<u8>>)>> {
+    let path = path.as_ref();
+    if ImageFormat::from_path(path).ok() == Some(ImageFormat::Gif) {
+        convert_gif(BufReader::new(File::open(path)?), options)
+    } else {
+        convert_image(&image::open(path)?, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        convert_gif, convert_image, gamma_table, resample, ImageOptions, Mapping, MAX_FPS,
+    };
+    use crate::Command;
+    use image::{
+        codecs::gif::GifEncoder, Delay, DynamicImage, Frame, Rgb, RgbImage, Rgba, RgbaImage,
+    };
+    extern crate std;
+    use std::{io::Cursor, vec, vec::Vec};
+
+    fn sprite_sheet() -> DynamicImage {
+        // Frame `y` lights LED `y` red
+        DynamicImage::ImageRgb8(RgbImage::from_fn(4, 3, |x, y| {
+            if x == y {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 0])
+            }
+        }))
+    }
+
+    fn gif(colours: &[[u8; 4]], delay_ms: u32) -> Vec<u8> {
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            for colour in colours {
+                let image = RgbaImage::from_pixel(2, 2, Rgba(*colour));
+                let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+                encoder
+                    .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+        gif
+    }
+
+    #[test]
+    fn converts_sprite_sheet_rows() {
+        let options = ImageOptions {
+            fps: Some(10.0),
+            ..Default::default()
+        };
+        let frames = convert_image(&sprite_sheet(), &options).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames[1],
+            (
+                100,
+                Command::Stream(vec![0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0])
+            )
+        );
+        assert_eq!(frames[2].0, 200);
+    }
+
+    #[test]
+    fn resizes_to_led_count() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(16, 1, Rgb([10, 20, 30])));
+        let options = ImageOptions {
+            width: Some(5),
+            ..Default::default()
+        };
+        let frames = convert_image(&image, &options).unwrap();
+        assert_eq!(frames, vec![(0, Command::Stream([10, 20, 30].repeat(5)))]);
+    }
+
+    #[test]
+    fn applies_gamma() {
+        let table = gamma_table(2.0);
+        assert_eq!((table[0], table[128], table[255]), (0, 64, 255));
+        assert_eq!(gamma_table(1.0)[77], 77);
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb([128, 255, 0])));
+        let options = ImageOptions {
+            gamma: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            convert_image(&image, &options).unwrap()[0].1,
+            Command::Stream(vec![64, 255, 0])
+        );
+    }
+
+    #[test]
+    fn samples_points() {
+        let options = ImageOptions {
+            mapping: Mapping::Points(vec![(3, 2), (0, 0), (9, 9)]),
+            ..Default::default()
+        };
+        let frames = convert_image(&sprite_sheet(), &options).unwrap();
+        assert_eq!(
+            frames,
+            vec![(0, Command::Stream(vec![0, 0, 0, 255, 0, 0, 0, 0, 0]))]
+        );
+    }
+
+    #[test]
+    fn keeps_gif_timing() {
+        let gif = gif(&[[255, 0, 0, 255], [0, 0, 255, 255]], 100);
+        let options = ImageOptions {
+            mapping: Mapping::Points(vec![(0, 0)]),
+            ..Default::default()
+        };
+        let frames = convert_gif(Cursor::new(&gif), &options).unwrap();
+        assert_eq!(
+            frames,
+            vec![
+                (0, Command::Stream(vec![255, 0, 0])),
+                (100, Command::Stream(vec![0, 0, 255]))
+            ]
+        );
+    }
+
+    #[test]
+    fn resamples_gif_frame_rate() {
+        let gif = gif(&[[255, 0, 0, 255], [0, 0, 255, 255]], 100);
+        let options = ImageOptions {
+            mapping: Mapping::Points(vec![(1, 1)]),
+            fps: Some(20.0),
+            ..Default::default()
+        };
+        let frames = convert_gif(Cursor::new(&gif), &options).unwrap();
+        let times: Vec<_> = frames.iter().map(|(at, _)| *at).collect();
+        assert_eq!(times, [0, 50, 100, 150]);
+        assert_eq!(frames[1].1, Command::Stream(vec![255, 0, 0]));
+        assert_eq!(frames[2].1, Command::Stream(vec![0, 0, 255]));
+    }
+
+    #[test]
+    fn given_invalid_options_fails() {
+        let gif = gif(&[[255, 0, 0, 255]], 100);
+        for fps in [0.0, -5.0, f32::NAN, f32::INFINITY, 1e30, MAX_FPS + 1.0] {
+            let options = ImageOptions {
+                mapping: Mapping::Points(vec![(0, 0)]),
+                fps: Some(fps),
+                ..Default::default()
+            };
+            assert!(
+                convert_gif(Cursor::new(&gif), &options).is_err(),
+                "fps {}",
+                fps
+            );
+            assert!(convert_image(&sprite_sheet(), &options).is_err());
+        }
+        for gamma in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let options = ImageOptions {
+                gamma,
+                ..Default::default()
+            };
+            assert!(options.validate().is_err(), "gamma {}", gamma);
+        }
+        assert!(ImageOptions::default().validate().is_ok());
+    }
+
+    #[test]
+    fn given_long_animation_resampling_fails() {
+        let frames = vec![(0, vec![1, 2, 3])];
+        assert_eq!(resample(&frames, 10, MAX_FPS).unwrap().len(), 10);
+        // 100 s at 1000 fps is the limit, a day is not
+        assert!(resample(&frames, 100_000, MAX_FPS).is_ok());
+        assert!(resample(&frames, 86_400_000, MAX_FPS).is_err());
+    }
+}
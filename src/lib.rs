@@ -1,24 +1,98 @@
 #![no_std]
+pub use builder::*;
 use core::fmt::{Display, Formatter, Result as FMTResult};
 pub use embedded::*;
 pub use error::*;
+pub use pixels::*;
+pub use sim::*;
 
+// Conversions in `owned` are trait impls, the glob keeps anything it makes public at the crate root
 #[cfg(feature = "owned")]
+#[allow(unused_imports)]
 pub use owned::*;
 
 #[cfg(feature = "owned")]
 mod owned;
 
+#[cfg(feature = "heapless_impl")]
+mod heapless_impl;
+
+#[cfg(feature = "arrayvec_impl")]
+mod arrayvec_impl;
+
+#[cfg(feature = "bytes_impl")]
+mod bytes_impl;
+
 #[cfg(feature = "serde_impl")]
 pub mod serde_impl;
 
 #[cfg(feature = "serde_impl")]
 pub use serde_impl::{de, ser};
 
+#[cfg(any(feature = "tokio_impl", feature = "embedded_io_impl", feature = "cli"))]
+mod framing;
+
+#[cfg(feature = "tokio_impl")]
+pub mod tokio_impl;
+
+#[cfg(feature = "tokio_impl")]
+pub use tokio_impl::{CodecError, CommandCodec};
+
+#[cfg(feature = "embedded_io_impl")]
+pub mod embedded_io_impl;
+
+#[cfg(feature = "embedded_io_impl")]
+pub use embedded_io_impl::{read_command, IoError};
+
+#[cfg(feature = "embedded_io_async_impl")]
+pub use embedded_io_impl::read_command_async;
+
+#[cfg(feature = "cli")]
+pub mod cli;
+
+#[cfg(feature = "sim_export")]
+pub mod sim_export;
+
+#[cfg(feature = "sim_export")]
+pub use sim_export::{ExportError, Frames};
+
+#[cfg(feature = "image_impl")]
+pub mod image_impl;
+
+#[cfg(any(feature = "e131_impl", feature = "artnet_impl"))]
+pub mod dmx;
+
+#[cfg(feature = "e131_impl")]
+pub mod e131;
+
+#[cfg(feature = "artnet_impl")]
+pub mod artnet;
+
+#[cfg(any(feature = "adalight_impl", feature = "tpm2_impl", feature = "opc_impl"))]
+mod frame;
+
+#[cfg(feature = "adalight_impl")]
+pub mod adalight;
+
+#[cfg(feature = "tpm2_impl")]
+pub mod tpm2;
+
+#[cfg(feature = "opc_impl")]
+pub mod opc;
+
+#[cfg(feature = "ddp_impl")]
+pub mod ddp;
+
+pub mod recording;
+
+mod builder;
+mod convert;
 mod embedded;
 mod error;
+mod pixels;
+mod sim;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Command<T> {
     Health,
     Constant {
@@ -35,42 +109,11 @@ pub enum Command<T> {
     },
 }
 
-impl<T: Clone> Clone for Command<T> {
-    fn clone(&self) -> Self {
-        match self {
-            Self::Health => Self::Health,
-            Self::Constant { led_count, colour } => Self::Constant {
-                led_count: *led_count,
-                colour: *colour,
-            },
-            Self::Stream(inner) => Self::Stream(inner.clone()),
-            Self::Pulse {
-                led_count,
-                start,
-                end,
-                frames,
-                period,
-            } => Self::Pulse {
-                led_count: *led_count,
-                start: *start,
-                end: *end,
-                frames: *frames,
-                period: *period,
-            },
-        }
-    }
-}
-
 impl<T: AsRef<[u8]>> Command<T> {
     /// Reports size of command variant in bytes
     /// Length of payload + 1 for command type
     pub fn size_in_bytes(&self) -> usize {
-        match self {
-            Command::Constant { .. } => 6,
-            Command::Stream(slice) => slice.as_ref().len() + 1,
-            Command::Pulse { .. } => 12,
-            Command::Health => 1,
-        }
+        self.as_ref().encoded_len()
     }
 }
 
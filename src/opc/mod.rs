@@ -0,0 +1,182 @@
+#![cfg(feature = "opc_impl")]
+//! Open Pixel Control messages, as sent by Fadecandy clients, Processing sketches and pixel mappers.
+//!
+//! A message is channel, command, big endian data length and data, usually over TCP port 7890.
+//! Command 0 carries RGB colours and becomes `Stream`, other commands are passed through as [`Message`].
+use crate::{Command, CommandError};
+use core::convert::TryFrom;
+
+pub const PORT: u16 = 7890;
+pub const HEADER_LEN: usize = 4;
+/// Channel every output listens to
+pub const BROADCAST: u8 = 0;
+/// Command carrying 8 bit RGB colours
+pub const SET_PIXEL_COLOURS: u8 = 0;
+/// Command carrying vendor specific data, starting with a 16 bit system ID
+pub const SYSTEM_EXCLUSIVE: u8 = 0xff;
+
+/// One OPC message, data borrows from the parsed buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message<'a> {
+    pub channel: u8,
+    pub command: u8,
+    pub data: &'a [u8],
+}
+
+/// Length of the message starting with `header`, so stream readers know how much to wait for
+pub fn message_len(header: [u8; HEADER_LEN]) -> usize {
+    HEADER_LEN + u16::from_be_bytes([header[2], header[3]]) as usize
+}
+
+impl<'a> Message<'a> {
+    /// Parses the message at the start of `bytes`, returns it with the number of bytes it took.
+    /// Fails with `MalformedPayload` when `bytes` ends before the message does
+    pub fn parse(bytes: &'a [u8]) -> Result<(Self, usize), CommandError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(CommandError::MalformedPayload);
+        }
+        let len = message_len([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let data = bytes
+            .get(HEADER_LEN..len)
+            .ok_or(CommandError::MalformedPayload)?;
+        let message = Self {
+            channel: bytes[0],
+            command: bytes[1],
+            data,
+        };
+        Ok((message, len))
+    }
+
+    /// Writes the message, returns number of bytes written.
+    /// Fails with `ValueOutOfRange` when data overflows the `u16` length
+    pub fn try_write(&self, buf: &mut [u8]) -> Result<usize, CommandError> {
+        if self.data.len() > u16::MAX as usize {
+            return Err(CommandError::ValueOutOfRange);
+        }
+        let len = HEADER_LEN + self.data.len();
+        let buf = buf.get_mut(..len).ok_or(CommandError::BufferTooSmall)?;
+        let [high, low] = (self.data.len() as u16).to_be_bytes();
+        buf[..HEADER_LEN].copy_from_slice(&[self.channel, self.command, high, low]);
+        buf[HEADER_LEN..].copy_from_slice(self.data);
+        Ok(len)
+    }
+}
+
+impl<'a> TryFrom<Message<'a>> for Command<&'a [u8]> {
+    type Error = CommandError;
+
+    /// Colours of [`SET_PIXEL_COLOURS`] become `Stream`, a trailing partial pixel is ignored as OPC asks.
+    /// Other commands fail with `InvalidHeader`
+    fn try_from(value: Message<'a>) -> Result<Self, Self::Error> {
+        if value.command != SET_PIXEL_COLOURS {
+            return Err(CommandError::InvalidHeader);
+        }
+        let len = value.data.len() - value.data.len() % 3;
+        Ok(Command::Stream(&value.data[..len]))
+    }
+}
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Writes `Stream` or `Constant` as set pixel colours message for `channel`,
+    /// returns number of bytes written.
+    ///
+    /// Fails with `InvalidHeader` for `Health` and `Pulse`, which have no OPC counterpart,
+    /// `MalformedPayload` for payloads that are not whole pixels and `ValueOutOfRange`
+    /// when colours overflow the `u16` length
+    pub fn try_write_opc(&self, channel: u8, buf: &mut [u8]) -> Result<usize, CommandError> {
+        let frame = buf
+            .get_mut(HEADER_LEN..)
+            .ok_or(CommandError::BufferTooSmall)?;
+        let len = self.write_frame(frame)?;
+        if len > u16::MAX as usize {
+            return Err(CommandError::ValueOutOfRange);
+        }
+        let [high, low] = (len as u16).to_be_bytes();
+        buf[..HEADER_LEN].copy_from_slice(&[channel, SET_PIXEL_COLOURS, high, low]);
+        Ok(HEADER_LEN + len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{message_len, Message, SYSTEM_EXCLUSIVE};
+    use crate::{Command, CommandError};
+    use core::convert::TryFrom;
+
+    /// Two synthetic messages laid out by hand from the OPC spec, not a capture: 2 LEDs on channel 1,
+    /// then a system exclusive message in the format Fadecandy uses for colour correction
+    const SPEC_MESSAGES: [u8; 19] = [
+        0x01, 0x00, 0x00, 0x06, 255, 0, 0, 0, 0, 255, // set pixel colours
+        0x00, 0xff, 0x00, 0x05, 0x00, 0x01, 0x00, 0x02, 0x03, // system exclusive
+    ];
+
+    #[test]
+    fn parses_spec_messages() {
+        let (first, len) = Message::parse(&SPEC_MESSAGES).unwrap();
+        assert_eq!(len, 10);
+        assert_eq!(
+            Command::try_from(first),
+            Ok(Command::Stream(&[255, 0, 0, 0, 0, 255][..]))
+        );
+        let (second, len) = Message::parse(&SPEC_MESSAGES[10..]).unwrap();
+        assert_eq!(len, 9);
+        assert_eq!(second.command, SYSTEM_EXCLUSIVE);
+        assert_eq!(second.data, &[0, 1, 0, 2, 3]);
+        assert_eq!(
+            Command::<&[u8]>::try_from(second),
+            Err(CommandError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn given_truncated_message_fails() {
+        assert_eq!(
+            Message::parse(&SPEC_MESSAGES[..3]),
+            Err(CommandError::MalformedPayload)
+        );
+        assert_eq!(
+            Message::parse(&SPEC_MESSAGES[..9]),
+            Err(CommandError::MalformedPayload)
+        );
+        assert_eq!(message_len([1, 0, 0, 6]), 10);
+    }
+
+    #[test]
+    fn ignores_partial_pixel() {
+        let message = Message {
+            channel: 0,
+            command: 0,
+            data: &[1, 2, 3, 4],
+        };
+        assert_eq!(
+            Command::try_from(message),
+            Ok(Command::Stream(&[1, 2, 3][..]))
+        );
+    }
+
+    #[test]
+    fn encodes_commands() {
+        let mut buf = [0_u8; 16];
+        let cmd = Command::Stream([255_u8, 0, 0, 0, 0, 255].as_ref());
+        assert_eq!(cmd.try_write_opc(1, &mut buf), Ok(10));
+        assert_eq!(buf[..10], SPEC_MESSAGES[..10]);
+        let cmd: Command<&[u8]> = Command::Constant {
+            led_count: 2,
+            colour: (7, 8, 9),
+        };
+        assert_eq!(cmd.try_write_opc(0, &mut buf), Ok(10));
+        assert_eq!(buf[..10], [0, 0, 0, 6, 7, 8, 9, 7, 8, 9]);
+        let outcome = Command::<&[u8]>::Health.try_write_opc(0, &mut buf);
+        assert_eq!(outcome, Err(CommandError::InvalidHeader));
+        let (sysex, _) = Message::parse(&SPEC_MESSAGES[10..]).unwrap();
+        assert_eq!(sysex.try_write(&mut buf), Ok(9));
+        assert_eq!(buf[..9], SPEC_MESSAGES[10..]);
+        assert_eq!(
+            sysex.try_write(&mut buf[..8]),
+            Err(CommandError::BufferTooSmall)
+        );
+    }
+}
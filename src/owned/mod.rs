@@ -1,29 +1,12 @@
 #![cfg(feature = "owned")]
 extern crate alloc;
 use crate::{Command, CommandError};
-use alloc::vec::Vec;
-use core::convert::TryFrom;
+use alloc::{borrow::Cow, vec::Vec};
+use core::{convert::TryFrom, iter::FromIterator};
 
 impl<'a> From<Command<&'a [u8]>> for Command<Vec<u8>> {
     fn from(src: Command<&'a [u8]>) -> Self {
-        match src {
-            Command::Health => Command::Health,
-            Command::Constant { led_count, colour } => Command::Constant { led_count, colour },
-            Command::Stream(bytes) => Command::Stream(bytes.to_vec()),
-            Command::Pulse {
-                led_count,
-                start,
-                end,
-                frames,
-                period,
-            } => Command::Pulse {
-                led_count,
-                start,
-                end,
-                frames,
-                period,
-            },
-        }
+        src.map(<[u8]>::to_vec)
     }
 }
 
@@ -33,3 +16,68 @@ impl<'a> TryFrom<&'a [u8]> for Command<Vec<u8>> {
         Command::try_from(value).map(|cmd: Command<&[u8]>| cmd.into())
     }
 }
+
+impl<T> Command<T>
+where
+    T: Into<Vec<u8>>,
+{
+    /// Takes ownership of the payload, copying it only when `T` does not own it already
+    pub fn into_owned(self) -> Command<Vec<u8>> {
+        self.map(Into::into)
+    }
+}
+
+impl<'a> From<Command<&'a [u8]>> for Command<Cow<'a, [u8]>> {
+    fn from(src: Command<&'a [u8]>) -> Self {
+        src.map(Cow::Borrowed)
+    }
+}
+
+impl From<Command<Vec<u8>>> for Command<Cow<'static, [u8]>> {
+    fn from(src: Command<Vec<u8>>) -> Self {
+        src.map(Cow::Owned)
+    }
+}
+
+/// Borrows `Stream` payload from `value`, `into_owned` detaches it later if needed
+impl<'a> TryFrom<&'a [u8]> for Command<Cow<'a, [u8]>> {
+    type Error = CommandError;
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        Command::<&[u8]>::try_from(value).map(Command::from)
+    }
+}
+
+/// Collects colours into a `Stream` payload
+impl FromIterator<(u8, u8, u8)> for Command<Vec<u8>> {
+    fn from_iter<I: IntoIterator<Item = (u8, u8, u8)>>(iter: I) -> Self {
+        Command::Stream(iter.into_iter().flat_map(|(r, g, b)| [r, g, b]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Command;
+    extern crate alloc;
+    use alloc::{borrow::Cow, vec, vec::Vec};
+    use core::convert::TryFrom;
+
+    #[test]
+    fn collects_stream_from_pixels() {
+        let cmd: Command<Vec<u8>> = (0..2_u8).map(|idx| (idx, idx, idx)).collect();
+        assert_eq!(cmd, Command::Stream(vec![0, 0, 0, 1, 1, 1]));
+    }
+
+    #[test]
+    fn converts_into_owned() {
+        let bytes = [b's', 1, 2, 3];
+        let cmd = Command::<Cow<[u8]>>::try_from(&bytes[..]).unwrap();
+        assert!(matches!(cmd, Command::Stream(Cow::Borrowed(_))));
+        assert_eq!(cmd.into_owned(), Command::Stream(vec![1, 2, 3]));
+        let cmd: Command<Cow<[u8]>> = Command::Stream(vec![1, 2, 3]).into();
+        assert!(matches!(cmd, Command::Stream(Cow::Owned(_))));
+        assert_eq!(
+            Command::Stream(&bytes[1..]).into_owned(),
+            Command::Stream(vec![1, 2, 3])
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::Command;
+use core::{
+    convert::TryInto,
+    iter::FusedIterator,
+    ops::Range,
+    slice::{ChunksExact, ChunksExactMut},
+};
+
+/// Scales channel by `scale / 256`, 255 keeps the value as is
+fn scale_channel(value: u8, scale: u8) -> u8 {
+    ((value as u16 * (scale as u16 + 1)) >> 8) as u8
+}
+
+/// Bytes of pixel `index`, `None` when they do not fit `usize`
+fn pixel_range(index: usize) -> Option<Range<usize>> {
+    let start = index.checked_mul(3)?;
+    Some(start..start.checked_add(3)?)
+}
+
+/// Iterator over colours of a `Stream` payload, see [`Command::pixels`]
+#[derive(Debug, Clone)]
+pub struct Pixels<'a> {
+    chunks: ChunksExact<'a, u8>,
+}
+
+impl<'a> Pixels<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            chunks: bytes.chunks_exact(3),
+        }
+    }
+}
+
+impl Iterator for Pixels<'_> {
+    type Item = (u8, u8, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(|rgb| (rgb[0], rgb[1], rgb[2]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.chunks.nth(n).map(|rgb| (rgb[0], rgb[1], rgb[2]))
+    }
+}
+
+impl DoubleEndedIterator for Pixels<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.chunks.next_back().map(|rgb| (rgb[0], rgb[1], rgb[2]))
+    }
+}
+
+impl ExactSizeIterator for Pixels<'_> {}
+
+impl FusedIterator for Pixels<'_> {}
+
+/// Mutable view over colours of an owned `Stream` payload, see [`Command::pixels_mut`].
+/// Transforms work in place, nothing is allocated.
+#[derive(Debug)]
+pub struct PixelsMut<'a> {
+    bytes: &'a mut [u8],
+}
+
+impl<'a> PixelsMut<'a> {
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 3
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<(u8, u8, u8)> {
+        let rgb = self.bytes.get(pixel_range(index)?)?;
+        Some((rgb[0], rgb[1], rgb[2]))
+    }
+
+    /// Returns `false` when `index` is out of range
+    pub fn set(&mut self, index: usize, colour: (u8, u8, u8)) -> bool {
+        match pixel_range(index).and_then(|range| self.bytes.get_mut(range)) {
+            Some(rgb) => {
+                rgb.copy_from_slice(&[colour.0, colour.1, colour.2]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn iter(&self) -> Pixels<'_> {
+        Pixels::new(self.bytes)
+    }
+
+    /// Iterates over pixels as `[r, g, b]` arrays that can be modified in place
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = &mut [u8; 3]> + ExactSizeIterator {
+        let chunks: ChunksExactMut<'_, u8> = self.bytes.chunks_exact_mut(3);
+        // chunks_exact_mut(3) only yields slices of 3 bytes
+        chunks.map(|rgb| rgb.try_into().unwrap())
+    }
+
+    pub fn fill(&mut self, colour: (u8, u8, u8)) {
+        for rgb in self.iter_mut() {
+            *rgb = [colour.0, colour.1, colour.2];
+        }
+    }
+
+    /// Reverses pixel order, channel order within a pixel is kept
+    pub fn reverse(&mut self) {
+        self.bytes.reverse();
+        for rgb in self.iter_mut() {
+            rgb.reverse();
+        }
+    }
+
+    /// Moves every pixel `count` positions towards the start, wrapping around
+    pub fn rotate_left(&mut self, count: usize) {
+        if !self.is_empty() {
+            self.bytes.rotate_left(count % self.len() * 3);
+        }
+    }
+
+    /// Moves every pixel `count` positions towards the end, wrapping around
+    pub fn rotate_right(&mut self, count: usize) {
+        if !self.is_empty() {
+            self.bytes.rotate_right(count % self.len() * 3);
+        }
+    }
+
+    /// Scales every channel by `scale / 256`, 255 leaves colours untouched and 0 turns them off
+    pub fn scale_brightness(&mut self, scale: u8) {
+        for channel in self.bytes.iter_mut() {
+            *channel = scale_channel(*channel, scale);
+        }
+    }
+}
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Colours of a `Stream` payload, `None` for other commands
+    pub fn pixels(&self) -> Option<Pixels<'_>> {
+        match self {
+            Command::Stream(bytes) => Some(Pixels::new(bytes.as_ref())),
+            _ => None,
+        }
+    }
+
+    /// Colour of `index`-th LED of a `Stream`
+    pub fn pixel(&self, index: usize) -> Option<(u8, u8, u8)> {
+        self.pixels()?.nth(index)
+    }
+}
+
+impl<T> Command<T>
+where
+    T: AsMut<[u8]>,
+{
+    /// Mutable view over colours of a `Stream` payload, `None` for other commands
+    pub fn pixels_mut(&mut self) -> Option<PixelsMut<'_>> {
+        match self {
+            Command::Stream(bytes) => {
+                let bytes = bytes.as_mut();
+                let len = bytes.len() - bytes.len() % 3;
+                Some(PixelsMut {
+                    bytes: &mut bytes[..len],
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scale_channel;
+    use crate::Command;
+
+    #[test]
+    fn iterates_pixels_both_ways() {
+        let cmd = Command::Stream([1_u8, 2, 3, 4, 5, 6, 7, 8, 9].as_ref());
+        let mut pixels = cmd.pixels().expect("Stream has pixels");
+        assert_eq!(pixels.len(), 3);
+        assert_eq!(pixels.next(), Some((1, 2, 3)));
+        assert_eq!(pixels.next_back(), Some((7, 8, 9)));
+        assert_eq!(pixels.len(), 1);
+        assert_eq!(pixels.next(), Some((4, 5, 6)));
+        assert_eq!(pixels.next(), None);
+    }
+
+    #[test]
+    fn indexes_pixels() {
+        let cmd = Command::Stream([1_u8, 2, 3, 4, 5, 6].as_ref());
+        assert_eq!(cmd.pixel(1), Some((4, 5, 6)));
+        assert_eq!(cmd.pixel(2), None);
+    }
+
+    #[test]
+    fn non_stream_has_no_pixels() {
+        let cmd: Command<&[u8]> = Command::Constant {
+            led_count: 2,
+            colour: (1, 2, 3),
+        };
+        assert!(cmd.pixels().is_none());
+        assert_eq!(cmd.pixel(0), None);
+    }
+
+    #[test]
+    fn sets_pixels_in_place() {
+        let mut storage = [0_u8; 6];
+        let mut cmd = Command::Stream(&mut storage[..]);
+        let mut pixels = cmd.pixels_mut().unwrap();
+        assert!(pixels.set(1, (7, 8, 9)));
+        assert!(!pixels.set(2, (7, 8, 9)));
+        assert!(!pixels.set(usize::MAX, (7, 8, 9)));
+        assert_eq!(pixels.get(1), Some((7, 8, 9)));
+        assert_eq!(pixels.get(usize::MAX), None);
+        assert_eq!(pixels.get(usize::MAX / 3), None);
+        assert_eq!(storage, [0, 0, 0, 7, 8, 9]);
+    }
+
+    #[test]
+    fn reverses_pixels() {
+        let mut storage = [1_u8, 2, 3, 4, 5, 6, 7, 8, 9];
+        Command::Stream(&mut storage[..])
+            .pixels_mut()
+            .unwrap()
+            .reverse();
+        assert_eq!(storage, [7, 8, 9, 4, 5, 6, 1, 2, 3]);
+    }
+
+    #[test]
+    fn rotates_pixels() {
+        let mut storage = [1_u8, 2, 3, 4, 5, 6, 7, 8, 9];
+        let mut cmd = Command::Stream(&mut storage[..]);
+        cmd.pixels_mut().unwrap().rotate_left(1);
+        assert_eq!(cmd.pixel(0), Some((4, 5, 6)));
+        cmd.pixels_mut().unwrap().rotate_right(4);
+        assert_eq!(storage, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn scales_brightness() {
+        assert_eq!(scale_channel(255, 255), 255);
+        assert_eq!(scale_channel(255, 0), 0);
+        assert_eq!(scale_channel(200, 127), 100);
+        let mut storage = [255_u8, 128, 0];
+        let mut cmd = Command::Stream(&mut storage[..]);
+        cmd.pixels_mut().unwrap().scale_brightness(127);
+        assert_eq!(storage, [127, 64, 0]);
+    }
+
+    #[test]
+    fn fills_and_iterates_mutably() {
+        let mut storage = [0_u8; 6];
+        let mut cmd = Command::Stream(&mut storage[..]);
+        let mut pixels = cmd.pixels_mut().unwrap();
+        pixels.fill((1, 2, 3));
+        for rgb in pixels.iter_mut().rev().take(1) {
+            rgb[0] = 9;
+        }
+        assert_eq!(pixels.iter().len(), 2);
+        assert_eq!(storage, [1, 2, 3, 9, 2, 3]);
+    }
+}
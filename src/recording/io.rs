@@ -0,0 +1,190 @@
+//! Recordings over `std::io`, decoding failures are reported as `io::ErrorKind::InvalidData`.
+extern crate std;
+use super::{
+    read_header, read_record_head, write_header, write_record, HEADER_LEN, MAX_RECORD_HEAD_LEN,
+};
+use crate::{Command, CommandError};
+use core::convert::TryFrom;
+use std::{
+    io::{self, Read, Write},
+    vec,
+    vec::Vec,
+};
+
+fn invalid_data(err: CommandError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Appends records to `W`
+pub struct RecordingWriter<W> {
+    writer: W,
+    last: u32,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> RecordingWriter<W> {
+    /// Writes the header, records are timed from this point on
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let mut header = [0_u8; HEADER_LEN];
+        write_header(&mut header).map_err(invalid_data)?;
+        writer.write_all(&header)?;
+        Ok(Self {
+            writer,
+            last: 0,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Records `command` that arrived `at` ms after the start, going back in time counts as no delay
+    pub fn record<T: AsRef<[u8]>>(&mut self, at: u32, command: &Command<T>) -> io::Result<()> {
+        let delta = at.saturating_sub(self.last);
+        self.buf
+            .resize(MAX_RECORD_HEAD_LEN + command.size_in_bytes(), 0);
+        let len = write_record(delta, command, &mut self.buf).map_err(invalid_data)?;
+        self.writer.write_all(&self.buf[..len])?;
+        self.last = self.last.max(at);
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Iterator over records read from `R`, yields milliseconds since the start and the command.
+/// Stops after the first error. Records are read a byte at a time, so `R` should be buffered.
+pub struct RecordingReader<R> {
+    reader: R,
+    at: u32,
+    done: bool,
+}
+
+impl<R: Read> RecordingReader<R> {
+    /// Reads and checks the header
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0_u8; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        read_header(&header).map_err(invalid_data)?;
+        Ok(Self {
+            reader,
+            at: 0,
+            done: false,
+        })
+    }
+
+    fn read(&mut self) -> io::Result<Option<(u32, Command<Vec<u8>>)>> {
+        let mut head = [0_u8; MAX_RECORD_HEAD_LEN];
+        let mut len = 0;
+        let (delta, size) = loop {
+            let mut byte = [0_u8; 1];
+            if self.reader.read(&mut byte)? == 0 {
+                return match len {
+                    0 => Ok(None),
+                    _ => Err(io::ErrorKind::UnexpectedEof.into()),
+                };
+            }
+            head[len] = byte[0];
+            len += 1;
+            match read_record_head(&head[..len]) {
+                Ok((delta, size, _)) => break (delta, size),
+                Err(CommandError::MalformedPayload) if len < head.len() => continue,
+                Err(err) => return Err(invalid_data(err)),
+            }
+        };
+        let mut encoded = vec![0_u8; size];
+        self.reader.read_exact(&mut encoded)?;
+        let command = Command::<Vec<u8>>::try_from(&encoded[..]).map_err(invalid_data)?;
+        self.at = self.at.saturating_add(delta);
+        Ok(Some((self.at, command)))
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = io::Result<(u32, Command<Vec<u8>>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read().transpose();
+        self.done = !matches!(record, Some(Ok(_)));
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecordingReader, RecordingWriter};
+    use crate::{recording::Records, Command};
+    extern crate std;
+    use std::{io, vec, vec::Vec};
+
+    fn session() -> Vec<(u32, Command<Vec<u8>>)> {
+        vec![
+            (5, Command::Health),
+            (
+                1000,
+                Command::Constant {
+                    led_count: 60,
+                    colour: (255, 0, 0),
+                },
+            ),
+            (1000, Command::Stream(vec![7; 900])),
+            (70_000, Command::Stream(vec![1, 2, 3])),
+        ]
+    }
+
+    #[test]
+    fn round_trips_session() {
+        let mut writer = RecordingWriter::new(Vec::new()).unwrap();
+        for (at, command) in session() {
+            writer.record(at, &command).unwrap();
+        }
+        let recording = writer.into_inner();
+        let read: Vec<_> = RecordingReader::new(&recording[..])
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(read, session());
+        // In memory reader agrees with the streaming one
+        let in_place: Vec<_> = Records::new(&recording)
+            .unwrap()
+            .map(|record| record.map(|(at, command)| (at, command.into())))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(in_place, session());
+    }
+
+    #[test]
+    fn given_truncated_recording_fails() {
+        let mut writer = RecordingWriter::new(Vec::new()).unwrap();
+        writer.record(300, &Command::Stream([1_u8, 2, 3])).unwrap();
+        let recording = writer.into_inner();
+        let mut reader = RecordingReader::new(&recording[..recording.len() - 1]).unwrap();
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(reader.next().is_none());
+        let err = RecordingReader::new(&b"RIFF\x01"[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn given_oversized_record_fails_without_allocating() {
+        // Size field claims 4 GiB
+        let recording = b"LEDR\x01\x00\xff\xff\xff\xff\x0fs";
+        let mut reader = RecordingReader::new(&recording[..]).unwrap();
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(reader.next().is_none());
+
+        let mut writer = RecordingWriter::new(Vec::new()).unwrap();
+        let err = writer
+            .record(0, &Command::Stream(vec![0; 65_535]))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
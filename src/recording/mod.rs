@@ -0,0 +1,219 @@
+//! Recorded command sessions.
+//!
+//! A recording starts with the [`MAGIC`] bytes and a [`VERSION`] byte, followed by records of
+//! - milliseconds since the previous record (since the start for the first one), LEB128
+//! - length of the command's native encoding, LEB128
+//! - the native encoding, see [`Command::try_write_bytes`]
+//!
+//! [`Records`] reads a recording in memory without copying, e.g. scenes kept in flash.
+use crate::{Command, CommandError};
+use core::convert::TryFrom;
+
+pub const MAGIC: [u8; 4] = *b"LEDR";
+pub const VERSION: u8 = 1;
+/// Size of [`MAGIC`] and [`VERSION`]
+pub const HEADER_LEN: usize = 5;
+/// Largest size of both LEB128 fields of a record
+pub const MAX_RECORD_HEAD_LEN: usize = 10;
+/// Largest native encoding a record may hold, same as the `u16` length prefix of byte streams allows.
+/// Keeps a corrupt size field from asking readers for gigabytes.
+pub const MAX_COMMAND_LEN: usize = u16::MAX as usize;
+
+fn write_leb128(mut value: u32, buf: &mut [u8]) -> Result<usize, CommandError> {
+    let mut len = 0;
+    loop {
+        let byte = buf.get_mut(len).ok_or(CommandError::BufferTooSmall)?;
+        len += 1;
+        if value < 0x80 {
+            *byte = value as u8;
+            return Ok(len);
+        }
+        *byte = (value as u8 & 0x7f) | 0x80;
+        value >>= 7;
+    }
+}
+
+fn read_leb128(bytes: &[u8]) -> Result<(u32, usize), CommandError> {
+    let mut value = 0_u32;
+    for (idx, byte) in bytes.iter().enumerate().take(5) {
+        let bits = (*byte & 0x7f) as u32;
+        if idx == 4 && bits > 0x0f {
+            return Err(CommandError::ValueOutOfRange);
+        }
+        value |= bits << (7 * idx);
+        if byte & 0x80 == 0 {
+            return Ok((value, idx + 1));
+        }
+    }
+    Err(CommandError::MalformedPayload)
+}
+
+/// Writes the recording header, returns number of bytes written
+pub fn write_header(buf: &mut [u8]) -> Result<usize, CommandError> {
+    let header = buf
+        .get_mut(..HEADER_LEN)
+        .ok_or(CommandError::BufferTooSmall)?;
+    header[..4].copy_from_slice(&MAGIC);
+    header[4] = VERSION;
+    Ok(HEADER_LEN)
+}
+
+/// Writes the record of `command` arriving `delta` ms after the previous one,
+/// returns number of bytes written. Fails with `ValueOutOfRange` for commands over [`MAX_COMMAND_LEN`]
+pub fn write_record<T>(
+    delta: u32,
+    command: &Command<T>,
+    buf: &mut [u8],
+) -> Result<usize, CommandError>
+where
+    T: AsRef<[u8]>,
+{
+    let size = command.size_in_bytes();
+    if size > MAX_COMMAND_LEN {
+        return Err(CommandError::ValueOutOfRange);
+    }
+    let mut len = write_leb128(delta, buf)?;
+    len += write_leb128(size as u32, &mut buf[len..])?;
+    len += command.try_write_bytes(&mut &mut buf[len..])?;
+    Ok(len)
+}
+
+/// Reads the head of a record, returns delta, size of the encoded command and length of the head.
+/// Fails with `ValueOutOfRange` for sizes over [`MAX_COMMAND_LEN`]
+pub fn read_record_head(bytes: &[u8]) -> Result<(u32, usize, usize), CommandError> {
+    let (delta, delta_len) = read_leb128(bytes)?;
+    let (size, size_len) = read_leb128(&bytes[delta_len..])?;
+    let size = size as usize;
+    if size > MAX_COMMAND_LEN {
+        return Err(CommandError::ValueOutOfRange);
+    }
+    Ok((delta, size, delta_len + size_len))
+}
+
+/// Checks the recording header
+pub fn read_header(bytes: &[u8]) -> Result<(), CommandError> {
+    match bytes.get(..HEADER_LEN) {
+        Some(header) if header[..4] == MAGIC && header[4] == VERSION => Ok(()),
+        Some(_) => Err(CommandError::InvalidHeader),
+        None => Err(CommandError::MalformedPayload),
+    }
+}
+
+/// Iterator over records of an in-memory recording, yields milliseconds since the start and the command.
+/// Stops after the first error.
+#[derive(Debug, Clone)]
+pub struct Records<'a> {
+    bytes: &'a [u8],
+    at: u32,
+}
+
+impl<'a> Records<'a> {
+    pub fn new(recording: &'a [u8]) -> Result<Self, CommandError> {
+        read_header(recording)?;
+        Ok(Self {
+            bytes: &recording[HEADER_LEN..],
+            at: 0,
+        })
+    }
+
+    fn read(&mut self) -> Result<(u32, Command<&'a [u8]>), CommandError> {
+        let (delta, size, head_len) = read_record_head(self.bytes)?;
+        let end = head_len
+            .checked_add(size)
+            .ok_or(CommandError::ValueOutOfRange)?;
+        let encoded = self
+            .bytes
+            .get(head_len..end)
+            .ok_or(CommandError::MalformedPayload)?;
+        let command = Command::try_from(encoded)?;
+        self.bytes = &self.bytes[end..];
+        self.at = self.at.saturating_add(delta);
+        Ok((self.at, command))
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<(u32, Command<&'a [u8]>), CommandError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let record = self.read();
+        if record.is_err() {
+            self.bytes = &[];
+        }
+        Some(record)
+    }
+}
+
+#[cfg(feature = "std")]
+mod io;
+
+#[cfg(feature = "std")]
+pub use io::{RecordingReader, RecordingWriter};
+
+#[cfg(test)]
+mod tests {
+    use super::{read_leb128, write_header, write_leb128, write_record, Records, HEADER_LEN};
+    use crate::{Command, CommandError};
+
+    #[test]
+    fn leb128_round_trips() {
+        let mut buf = [0_u8; 5];
+        for value in [0, 127, 128, 300, u16::MAX as u32, u32::MAX].iter() {
+            let len = write_leb128(*value, &mut buf).unwrap();
+            assert_eq!(read_leb128(&buf[..len]), Ok((*value, len)));
+        }
+        assert_eq!(
+            write_leb128(300, &mut buf[..1]),
+            Err(CommandError::BufferTooSmall)
+        );
+        assert_eq!(
+            read_leb128(&[0x80, 0x80]),
+            Err(CommandError::MalformedPayload)
+        );
+        assert_eq!(
+            read_leb128(&[0xff, 0xff, 0xff, 0xff, 0x7f]),
+            Err(CommandError::ValueOutOfRange)
+        );
+    }
+
+    #[test]
+    fn reads_recording_in_place() {
+        let mut buf = [0_u8; 64];
+        let mut len = write_header(&mut buf).unwrap();
+        len += write_record(0, &Command::<&[u8]>::Health, &mut buf[len..]).unwrap();
+        len += write_record(300, &Command::Stream(&[1_u8, 2, 3][..]), &mut buf[len..]).unwrap();
+        assert_eq!(
+            &buf[HEADER_LEN..len],
+            &[0, 1, b'h', 0xac, 0x02, 4, b's', 1, 2, 3]
+        );
+
+        let mut records = Records::new(&buf[..len]).unwrap();
+        assert_eq!(records.next(), Some(Ok((0, Command::Health))));
+        assert_eq!(
+            records.next(),
+            Some(Ok((300, Command::Stream(&[1_u8, 2, 3][..]))))
+        );
+        assert_eq!(records.next(), None);
+    }
+
+    #[test]
+    fn given_bad_recording_fails() {
+        assert_eq!(
+            Records::new(b"LEDX\x01").unwrap_err(),
+            CommandError::InvalidHeader
+        );
+        assert_eq!(
+            Records::new(b"LED").unwrap_err(),
+            CommandError::MalformedPayload
+        );
+        let mut records = Records::new(b"LEDR\x01\x00\x06c\x00").unwrap();
+        assert_eq!(records.next(), Some(Err(CommandError::MalformedPayload)));
+        assert_eq!(records.next(), None);
+        // Size over MAX_COMMAND_LEN
+        let mut records = Records::new(b"LEDR\x01\x00\x80\x80\x04s").unwrap();
+        assert_eq!(records.next(), Some(Err(CommandError::ValueOutOfRange)));
+    }
+}
@@ -1,17 +1,172 @@
 #![cfg(feature = "serde_impl")]
+pub mod colour;
+pub(crate) mod payload;
+#[cfg(feature = "postcard_impl")]
+mod postcard_impl;
+#[cfg(feature = "schema")]
+pub mod schema;
+
 pub mod ser {
+    use super::{
+        colour::to_hex,
+        payload::{Base64, Hex, BASE64_PREFIX, HEX_PREFIX},
+    };
     use crate::Command;
-    use serde::{ser::SerializeMap, Serialize};
+    use serde::{
+        ser::{self, SerializeMap, SerializeStructVariant},
+        Serialize, Serializer,
+    };
 
-    impl<T> Serialize for Command<T>
+    /// How human readable formats write `colour`, `start` and `end`
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    pub enum ColourFormat {
+        /// `[0,255,0]`
+        #[default]
+        Array,
+        /// `"#00ff00"`
+        Hex,
+    }
+
+    /// How human readable formats write `Stream` bytes
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    pub enum BytesFormat {
+        /// `[0,127,255]`
+        #[default]
+        Array,
+        /// `"hex:007fff"`
+        Hex,
+        /// `"base64:AH//"`
+        Base64,
+    }
+
+    /// Human readable serialization settings, binary formats ignore them
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    pub struct Options {
+        pub colour: ColourFormat,
+        pub bytes: BytesFormat,
+    }
+
+    /// Serializable view of a command with non default [`Options`],
+    /// created by [`Command::serialize_with`]
+    pub struct WithOptions<'a, T> {
+        command: &'a Command<T>,
+        options: Options,
+    }
+
+    impl<T> Command<T>
     where
         T: AsRef<[u8]>,
     {
+        pub fn serialize_with(&self, options: Options) -> WithOptions<'_, T> {
+            WithOptions {
+                command: self,
+                options,
+            }
+        }
+    }
+
+    struct Colour((u8, u8, u8), ColourFormat);
+
+    impl Serialize for Colour {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match self.1 {
+                ColourFormat::Array => self.0.serialize(serializer),
+                ColourFormat::Hex => {
+                    let hex = to_hex(self.0);
+                    let hex = core::str::from_utf8(&hex).map_err(ser::Error::custom)?;
+                    serializer.serialize_str(hex)
+                }
+            }
+        }
+    }
+
+    /// Stream payload, written as a single byte blob by binary formats
+    struct RawBytes<'a>(&'a [u8]);
+
+    impl Serialize for RawBytes<'_> {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
-            S: serde::Serializer,
+            S: Serializer,
         {
-            match self {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    /// Non human readable formats (postcard, bincode, CBOR) get an externally tagged enum,
+    /// so the variant is encoded as an index instead of a "type" string.
+    fn serialize_compact<T, S>(command: &Command<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]>,
+        S: Serializer,
+    {
+        match command {
+            Command::Health => serializer.serialize_unit_variant("Command", 0, "health"),
+            Command::Constant { led_count, colour } => {
+                let mut state = serializer.serialize_struct_variant("Command", 1, "constant", 2)?;
+                state.serialize_field("led_count", led_count)?;
+                state.serialize_field("colour", colour)?;
+                state.end()
+            }
+            Command::Stream(inner) => serializer.serialize_newtype_variant(
+                "Command",
+                2,
+                "stream",
+                &RawBytes(inner.as_ref()),
+            ),
+            Command::Pulse {
+                led_count,
+                start,
+                end,
+                frames,
+                period,
+            } => {
+                let mut state = serializer.serialize_struct_variant("Command", 3, "pulse", 5)?;
+                state.serialize_field("led_count", led_count)?;
+                state.serialize_field("start", start)?;
+                state.serialize_field("end", end)?;
+                state.serialize_field("frames", frames)?;
+                state.serialize_field("period", period)?;
+                state.end()
+            }
+        }
+    }
+
+    struct Payload<'a>(&'a [u8], BytesFormat);
+
+    impl Serialize for Payload<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match self.1 {
+                BytesFormat::Array => self.0.serialize(serializer),
+                BytesFormat::Hex => {
+                    serializer.collect_str(&format_args!("{}{}", HEX_PREFIX, Hex(self.0)))
+                }
+                BytesFormat::Base64 => {
+                    serializer.collect_str(&format_args!("{}{}", BASE64_PREFIX, Base64(self.0)))
+                }
+            }
+        }
+    }
+
+    impl<T> Serialize for WithOptions<'_, T>
+    where
+        T: AsRef<[u8]>,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            if !serializer.is_human_readable() {
+                return serialize_compact(self.command, serializer);
+            }
+
+            let colour_format = self.options.colour;
+            match self.command {
                 Command::Health => {
                     let mut state = serializer.serialize_map(Some(1))?;
                     state.serialize_entry("type", "health")?;
@@ -21,13 +176,13 @@ pub mod ser {
                     let mut state = serializer.serialize_map(Some(3))?;
                     state.serialize_entry("type", "constant")?;
                     state.serialize_entry("led_count", led_count)?;
-                    state.serialize_entry("colour", colour)?;
+                    state.serialize_entry("colour", &Colour(*colour, colour_format))?;
                     state.end()
                 }
                 Command::Stream(inner) => {
                     let mut state = serializer.serialize_map(Some(2))?;
                     state.serialize_entry("type", "stream")?;
-                    state.serialize_entry("bytes", inner.as_ref())?;
+                    state.serialize_entry("bytes", &Payload(inner.as_ref(), self.options.bytes))?;
                     state.end()
                 }
                 Command::Pulse {
@@ -40,8 +195,8 @@ pub mod ser {
                     let mut state = serializer.serialize_map(Some(6))?;
                     state.serialize_entry("type", "pulse")?;
                     state.serialize_entry("led_count", led_count)?;
-                    state.serialize_entry("start", start)?;
-                    state.serialize_entry("end", end)?;
+                    state.serialize_entry("start", &Colour(*start, colour_format))?;
+                    state.serialize_entry("end", &Colour(*end, colour_format))?;
                     state.serialize_entry("frames", frames)?;
                     state.serialize_entry("period", period)?;
                     state.end()
@@ -49,30 +204,340 @@ pub mod ser {
             }
         }
     }
+
+    impl<T> Serialize for Command<T>
+    where
+        T: AsRef<[u8]>,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            self.serialize_with(Options::default())
+                .serialize(serializer)
+        }
+    }
 }
 
 pub mod de {
+    use super::{colour::parse_colour, payload::Decoded};
     use crate::Command;
     use core::{
+        convert::TryFrom,
         fmt::{Formatter, Result as FMTResult},
         marker::PhantomData,
     };
     use serde::{
-        de::{self, MapAccess, Visitor},
-        Deserialize,
+        de::{
+            self,
+            value::{BorrowedBytesDeserializer, SeqAccessDeserializer, SeqDeserializer},
+            EnumAccess, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
+        },
+        forward_to_deserialize_any, Deserialize, Deserializer,
     };
 
-    #[derive(Default)]
+    const VARIANTS: &[&str] = &["health", "constant", "stream", "pulse"];
+    const CONSTANT_FIELDS: &[&str] = &["led_count", "colour"];
+    const PULSE_FIELDS: &[&str] = &["led_count", "start", "end", "frames", "period"];
+    const STREAM_FIELDS: &[&str] = &["bytes"];
+    const FIELDS: &[&str] = &[
+        "type",
+        "led_count",
+        "start",
+        "end",
+        "colour",
+        "frames",
+        "period",
+        "bytes",
+    ];
+
+    #[derive(Clone, Copy)]
     enum CommandVariant {
-        #[default]
         Health,
         Constant,
         Stream,
         Pulse,
     }
 
+    struct CommandVariantVisitor;
+
+    impl<'de> Visitor<'de> for CommandVariantVisitor {
+        type Value = CommandVariant;
+
+        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
+            formatter.write_str("Command type name or index")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            match v {
+                0 => Ok(CommandVariant::Health),
+                1 => Ok(CommandVariant::Constant),
+                2 => Ok(CommandVariant::Stream),
+                3 => Ok(CommandVariant::Pulse),
+                _ => Err(de::Error::invalid_value(Unexpected::Unsigned(v), &self)),
+            }
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            match v {
+                "constant" => Ok(CommandVariant::Constant),
+                "pulse" => Ok(CommandVariant::Pulse),
+                "health" => Ok(CommandVariant::Health),
+                "stream" => Ok(CommandVariant::Stream),
+                _ => Err(de::Error::custom("Unexpected command type")),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CommandVariant {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_identifier(CommandVariantVisitor)
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    enum Field {
+        Type,
+        LedCount,
+        Start,
+        End,
+        Colour,
+        Frames,
+        Period,
+        Bytes,
+    }
+
+    struct FieldVisitor;
+
+    impl<'de> Visitor<'de> for FieldVisitor {
+        type Value = Field;
+
+        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
+            formatter.write_str("Command field name")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            match v {
+                "type" => Ok(Field::Type),
+                "led_count" => Ok(Field::LedCount),
+                "start" => Ok(Field::Start),
+                "end" => Ok(Field::End),
+                "colour" => Ok(Field::Colour),
+                "frames" => Ok(Field::Frames),
+                "period" => Ok(Field::Period),
+                "bytes" => Ok(Field::Bytes),
+                _ => Err(de::Error::unknown_field(v, FIELDS)),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Field {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_identifier(FieldVisitor)
+        }
+    }
+
+    enum Channel {
+        R,
+        G,
+        B,
+    }
+
+    struct ChannelVisitor;
+
+    impl<'de> Visitor<'de> for ChannelVisitor {
+        type Value = Channel;
+
+        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
+            formatter.write_str("Colour channel r, g or b")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            match v {
+                "r" => Ok(Channel::R),
+                "g" => Ok(Channel::G),
+                "b" => Ok(Channel::B),
+                _ => Err(de::Error::unknown_field(v, &["r", "g", "b"])),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Channel {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_identifier(ChannelVisitor)
+        }
+    }
+
+    /// Colour in any notation human readable formats accept:
+    /// `[0,255,0]`, `"#00ff00"`, `"00ff00"`, `"lime"` or `{"r":0,"g":255,"b":0}`
+    struct AnyColour((u8, u8, u8));
+
+    struct AnyColourVisitor;
+
+    impl<'de> Visitor<'de> for AnyColourVisitor {
+        type Value = AnyColour;
+
+        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
+            formatter.write_str("Colour as [r, g, b], hex string, CSS name or {r, g, b} map")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            parse_colour(v)
+                .map(AnyColour)
+                .ok_or_else(|| de::Error::invalid_value(Unexpected::Str(v), &self))
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let r = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let g = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            let b = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+            if seq.next_element::<de::IgnoredAny>()?.is_some() {
+                return Err(de::Error::invalid_length(4, &self));
+            }
+            Ok(AnyColour((r, g, b)))
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let (mut r, mut g, mut b) = (None, None, None);
+            while let Some(channel) = map.next_key()? {
+                match channel {
+                    Channel::R => r = Some(map.next_value()?),
+                    Channel::G => g = Some(map.next_value()?),
+                    Channel::B => b = Some(map.next_value()?),
+                }
+            }
+            let r = r.ok_or_else(|| de::Error::missing_field("r"))?;
+            let g = g.ok_or_else(|| de::Error::missing_field("g"))?;
+            let b = b.ok_or_else(|| de::Error::missing_field("b"))?;
+            Ok(AnyColour((r, g, b)))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for AnyColour {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            if deserializer.is_human_readable() {
+                deserializer.deserialize_any(AnyColourVisitor)
+            } else {
+                Deserialize::deserialize(deserializer).map(AnyColour)
+            }
+        }
+    }
+
+    /// Stream payload as a byte array, `hex:`/`base64:` prefixed or plain base64 string
+    struct AnyBytes<T>(T);
+
+    struct AnyBytesVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> Visitor<'de> for AnyBytesVisitor<T>
+    where
+        T: Deserialize<'de>,
+    {
+        type Value = AnyBytes<T>;
+
+        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
+            formatter.write_str(
+                "Byte array, \"hex:\" or \"base64:\" prefixed string, or base64 string that is not also hex",
+            )
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            let decoded = Decoded::new(v)
+                .ok_or_else(|| de::Error::invalid_value(Unexpected::Str(v), &self))?;
+            T::deserialize(SeqDeserializer::new(decoded)).map(AnyBytes)
+        }
+
+        fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            T::deserialize(SeqAccessDeserializer::new(seq)).map(AnyBytes)
+        }
+
+        fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+            T::deserialize(BorrowedBytesDeserializer::new(v)).map(AnyBytes)
+        }
+    }
+
+    impl<'de, T> Deserialize<'de> for AnyBytes<T>
+    where
+        T: Deserialize<'de>,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            if deserializer.is_human_readable() {
+                deserializer.deserialize_any(AnyBytesVisitor(PhantomData))
+            } else {
+                T::deserialize(deserializer).map(AnyBytes)
+            }
+        }
+    }
+
+    fn next_colour<'de, A>(map: &mut A) -> Result<Option<(u8, u8, u8)>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        Ok(map
+            .next_value::<Option<AnyColour>>()?
+            .map(|colour| colour.0))
+    }
+
+    /// `Stream` payload decoded from the native encoding, handed over to `T`
+    /// as borrowed bytes or as a sequence, whichever `T` asks for.
+    struct NativePayload<'de, E>(&'de [u8], PhantomData<E>);
+
+    impl<'de, E: de::Error> Deserializer<'de> for NativePayload<'de, E> {
+        type Error = E;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_borrowed_bytes(self.0)
+        }
+
+        fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, E>
+        where
+            V: Visitor<'de>,
+        {
+            SeqDeserializer::new(self.0.iter().copied()).deserialize_any(visitor)
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
     struct CommandVisitor<'a, T> {
-        cmd_variant: CommandVariant,
+        lenient: bool,
+        cmd_variant: Option<CommandVariant>,
         led_count: Option<u16>,
         colour: Option<(u8, u8, u8)>,
         start: Option<(u8, u8, u8)>,
@@ -80,13 +545,16 @@ pub mod de {
         frames: Option<u8>,
         period: Option<u16>,
         bytes: Option<T>,
+        /// Keys present in the map indexed by `Field`, `null` values count
+        seen: [bool; 8],
         _pd: PhantomData<&'a u8>,
     }
 
     impl<'a, T> Default for CommandVisitor<'a, T> {
         fn default() -> Self {
             Self {
-                cmd_variant: Default::default(),
+                lenient: false,
+                cmd_variant: None,
                 led_count: None,
                 colour: None,
                 start: None,
@@ -94,73 +562,93 @@ pub mod de {
                 frames: None,
                 period: None,
                 bytes: None,
+                seen: [false; 8],
                 _pd: Default::default(),
             }
         }
     }
 
-    impl<'a, T> CommandVisitor<'a, T> {
-        fn resolve_cmd_type<'de, E: de::Error>(
-            &mut self,
-            mut map: impl MapAccess<'de, Error = E>,
-        ) -> Result<(), E> {
-            match map.next_value()? {
-                "constant" => self.cmd_variant = CommandVariant::Constant,
-                "pulse" => self.cmd_variant = CommandVariant::Pulse,
-                "health" => self.cmd_variant = CommandVariant::Health,
-                "stream" => self.cmd_variant = CommandVariant::Stream,
-                _ => return Err(de::Error::custom("Unexpected command type")),
+    impl<'a, T> CommandVisitor<'a, T>
+    where
+        T: AsRef<[u8]>,
+    {
+        fn for_variant(cmd_variant: CommandVariant) -> Self {
+            Self {
+                cmd_variant: Some(cmd_variant),
+                ..Default::default()
             }
-
-            Ok(())
         }
-    }
 
-    impl<'de: 'a, 'a, T> Visitor<'de> for CommandVisitor<'a, T>
-    where
-        T: AsRef<[u8]> + Deserialize<'de>,
-    {
-        type Value = Command<T>;
+        fn lenient() -> Self {
+            Self {
+                lenient: true,
+                ..Default::default()
+            }
+        }
 
-        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
-            formatter.write_str("Map or sequence of bytes")
+        /// Rejects fields the variant does not use
+        fn check_fields<E: de::Error>(&self, cmd_variant: CommandVariant) -> Result<(), E> {
+            let expected = match cmd_variant {
+                CommandVariant::Health => &[],
+                CommandVariant::Constant => CONSTANT_FIELDS,
+                CommandVariant::Stream => STREAM_FIELDS,
+                CommandVariant::Pulse => PULSE_FIELDS,
+            };
+            let present = [
+                ("led_count", Field::LedCount),
+                ("colour", Field::Colour),
+                ("start", Field::Start),
+                ("end", Field::End),
+                ("frames", Field::Frames),
+                ("period", Field::Period),
+                ("bytes", Field::Bytes),
+            ];
+            match present
+                .iter()
+                .find(|(field, key)| self.seen[*key as usize] && !expected.contains(field))
+            {
+                Some((field, _)) => Err(de::Error::unknown_field(field, expected)),
+                None => Ok(()),
+            }
         }
 
-        fn visit_map<A>(mut self, mut map: A) -> Result<Self::Value, A::Error>
+        fn read_map<'de, A>(&mut self, mut map: A) -> Result<(), A::Error>
         where
-            A: serde::de::MapAccess<'de>,
+            A: MapAccess<'de>,
+            T: Deserialize<'de>,
         {
-            while let Some(key) = map.next_key()? {
+            while let Some(key) = map.next_key::<Field>()? {
+                self.seen[key as usize] = true;
                 match key {
-                    "type" => {
-                        self.resolve_cmd_type(&mut map)?;
-                    }
-                    "led_count" => self.led_count = map.next_value()?,
-                    "start" => self.start = map.next_value()?,
-                    "end" => self.end = map.next_value()?,
-                    "colour" => self.colour = map.next_value()?,
-                    "frames" => self.frames = map.next_value()?,
-                    "period" => self.period = map.next_value()?,
-                    "bytes" => self.bytes = map.next_value()?,
-                    _ => {
-                        return Err(de::Error::unknown_field(
-                            key,
-                            &[
-                                "type",
-                                "led_count",
-                                "start",
-                                "end",
-                                "colour",
-                                "frames",
-                                "period",
-                                "bytes",
-                            ],
-                        ))
+                    Field::Type => self.cmd_variant = Some(map.next_value()?),
+                    Field::LedCount => self.led_count = map.next_value()?,
+                    Field::Start => self.start = next_colour(&mut map)?,
+                    Field::End => self.end = next_colour(&mut map)?,
+                    Field::Colour => self.colour = next_colour(&mut map)?,
+                    Field::Frames => self.frames = map.next_value()?,
+                    Field::Period => self.period = map.next_value()?,
+                    Field::Bytes => {
+                        self.bytes = map
+                            .next_value::<Option<AnyBytes<T>>>()?
+                            .map(|bytes| bytes.0)
                     }
                 }
             }
 
-            match self.cmd_variant {
+            Ok(())
+        }
+
+        fn build<E: de::Error>(self) -> Result<Command<T>, E> {
+            let cmd_variant = match self.cmd_variant {
+                Some(cmd_variant) => cmd_variant,
+                None if self.lenient => CommandVariant::Health,
+                None => return Err(de::Error::missing_field("type")),
+            };
+            if !self.lenient {
+                self.check_fields(cmd_variant)?;
+            }
+
+            match cmd_variant {
                 CommandVariant::Health => Ok(Command::Health),
                 CommandVariant::Constant => {
                     let colour = self
@@ -209,24 +697,218 @@ pub mod de {
         }
     }
 
-    impl<'de: 'a, 'a, T> Deserialize<'de> for Command<T>
+    impl<'de: 'a, 'a, T> Visitor<'de> for CommandVisitor<'a, T>
+    where
+        T: AsRef<[u8]> + Deserialize<'de>,
+    {
+        type Value = Command<T>;
+
+        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
+            formatter.write_str("Map or sequence of bytes")
+        }
+
+        fn visit_map<A>(mut self, map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            self.read_map(map)?;
+            self.build()
+        }
+
+        fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+            let command = Command::<&[u8]>::try_from(v).map_err(de::Error::custom)?;
+            command.try_map(|bytes| T::deserialize(NativePayload(bytes, PhantomData)))
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            let command = Command::<&[u8]>::try_from(v).map_err(de::Error::custom)?;
+            command.try_map(|bytes| T::deserialize(SeqDeserializer::new(bytes.iter().copied())))
+        }
+
+        /// Native encoding delivered byte by byte, e.g. JSON array of numbers.
+        /// Fixed size commands are collected on the stack, `Stream` payload goes straight to `T`.
+        fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let header: u8 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            if header == b's' {
+                self.cmd_variant = Some(CommandVariant::Stream);
+                self.bytes = Some(T::deserialize(SeqAccessDeserializer::new(seq))?);
+                return self.build();
+            }
+
+            let mut buf = [header; 12];
+            let mut len = 1;
+            while let Some(byte) = seq.next_element()? {
+                if len == buf.len() {
+                    return Err(de::Error::invalid_length(len + 1, &self));
+                }
+                buf[len] = byte;
+                len += 1;
+            }
+            let command = Command::<&[u8]>::try_from(&buf[..len]).map_err(de::Error::custom)?;
+            command.try_map(|_| Err(de::Error::custom("Stream payload in fixed size command")))
+        }
+    }
+
+    /// Command read from a byte string holding the native `try_write_bytes` encoding.
+    /// Lets formats without a notion of `Command` (postcard, bincode, CBOR byte strings) carry native frames,
+    /// `Native<&[u8]>` borrows `Stream` payload from the input where the format allows it.
+    #[derive(Debug, PartialEq)]
+    pub struct Native<T>(pub Command<T>);
+
+    impl<'de, T> Deserialize<'de> for Native<T>
+    where
+        T: AsRef<[u8]> + Deserialize<'de>,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer
+                .deserialize_bytes(CommandVisitor::default())
+                .map(Native)
+        }
+    }
+
+    /// Command deserialized without strict checks:
+    /// missing "type" means `health` and fields the variant does not use are ignored.
+    /// Binary formats are unaffected.
+    #[derive(Debug, PartialEq)]
+    pub struct Lenient<T>(pub Command<T>);
+
+    impl<'de, T> Deserialize<'de> for Lenient<T>
+    where
+        T: AsRef<[u8]> + Deserialize<'de>,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            if deserializer.is_human_readable() {
+                deserializer
+                    .deserialize_any(CommandVisitor::lenient())
+                    .map(Lenient)
+            } else {
+                Command::deserialize(deserializer).map(Lenient)
+            }
+        }
+    }
+
+    /// Reads fields of a variant already identified by the enum tag.
+    /// Self-describing formats hand over a map, postcard and bincode a sequence in declaration order.
+    struct VariantFieldsVisitor<'a, T>(CommandVisitor<'a, T>);
+
+    impl<'de: 'a, 'a, T> Visitor<'de> for VariantFieldsVisitor<'a, T>
+    where
+        T: AsRef<[u8]> + Deserialize<'de>,
+    {
+        type Value = Command<T>;
+
+        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
+            formatter.write_str("Command fields")
+        }
+
+        fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let fields = &mut self.0;
+            match fields.cmd_variant {
+                Some(CommandVariant::Constant) => {
+                    fields.led_count = seq.next_element()?;
+                    fields.colour = seq.next_element()?;
+                }
+                Some(CommandVariant::Pulse) => {
+                    fields.led_count = seq.next_element()?;
+                    fields.start = seq.next_element()?;
+                    fields.end = seq.next_element()?;
+                    fields.frames = seq.next_element()?;
+                    fields.period = seq.next_element()?;
+                }
+                _ => {}
+            }
+
+            self.0.build()
+        }
+
+        fn visit_map<A>(mut self, map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            self.0.read_map(map)?;
+            self.0.build()
+        }
+    }
+
+    struct CompactVisitor<'a, T>(PhantomData<&'a T>);
+
+    impl<'de: 'a, 'a, T> Visitor<'de> for CompactVisitor<'a, T>
+    where
+        T: AsRef<[u8]> + Deserialize<'de>,
+    {
+        type Value = Command<T>;
+
+        fn expecting(&self, formatter: &mut Formatter) -> FMTResult {
+            formatter.write_str("Command variant")
+        }
+
+        fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+        where
+            A: EnumAccess<'de>,
+        {
+            let (cmd_variant, variant) = data.variant()?;
+            match cmd_variant {
+                CommandVariant::Health => {
+                    variant.unit_variant()?;
+                    Ok(Command::Health)
+                }
+                CommandVariant::Stream => {
+                    let mut fields = CommandVisitor::<T>::for_variant(CommandVariant::Stream);
+                    fields.bytes = Some(variant.newtype_variant()?);
+                    fields.build()
+                }
+                CommandVariant::Constant => variant.struct_variant(
+                    CONSTANT_FIELDS,
+                    VariantFieldsVisitor(CommandVisitor::for_variant(cmd_variant)),
+                ),
+                CommandVariant::Pulse => variant.struct_variant(
+                    PULSE_FIELDS,
+                    VariantFieldsVisitor(CommandVisitor::for_variant(cmd_variant)),
+                ),
+            }
+        }
+    }
+
+    impl<'de, T> Deserialize<'de> for Command<T>
     where
         T: AsRef<[u8]> + Deserialize<'de>,
     {
         fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         where
-            D: serde::Deserializer<'de>,
+            D: Deserializer<'de>,
         {
-            deserializer.deserialize_map(CommandVisitor::default())
+            if deserializer.is_human_readable() {
+                deserializer.deserialize_any(CommandVisitor::default())
+            } else {
+                deserializer.deserialize_enum("Command", VARIANTS, CompactVisitor(PhantomData))
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{
+        de::{Lenient, Native},
+        ser::{BytesFormat, ColourFormat, Options},
+    };
     use crate::Command;
     extern crate std;
-    use std::{vec, vec::Vec};
+    use std::{format, vec, vec::Vec};
     type Plh = Vec<u8>;
 
     #[test]
@@ -329,4 +1011,366 @@ mod tests {
         let _: Command<Plh> =
             serde_json::from_str("{\"type\": \"stream\", \"bytes\": [127, 0]}").unwrap();
     }
+
+    fn binary_cases() -> Vec<Command<Plh>> {
+        vec![
+            Command::Health,
+            Command::Constant {
+                led_count: 1,
+                colour: (0, 126, 0),
+            },
+            Command::Stream(vec![0, 127, 0, 1, 2, 3]),
+            Command::Pulse {
+                led_count: 5,
+                start: (0, 0, 0),
+                end: (127, 0, 127),
+                frames: 60,
+                period: 2000,
+            },
+        ]
+    }
+
+    #[test]
+    fn bincode_e2e() {
+        for command in binary_cases() {
+            let serialized = bincode::serialize(&command).expect("Failed to serialize bincode");
+            let deserialized: Command<Plh> =
+                bincode::deserialize(&serialized).expect("Failed to deserialize bincode");
+            assert_eq!(command, deserialized);
+        }
+    }
+
+    #[test]
+    fn bincode_uses_variant_index() {
+        let serialized = bincode::serialize(&Command::<Plh>::Health).unwrap();
+        assert_eq!(serialized, vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn cbor_e2e() {
+        for command in binary_cases() {
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&command, &mut serialized)
+                .expect("Failed to serialize cbor");
+            let deserialized: Command<Plh> = ciborium::de::from_reader(serialized.as_slice())
+                .expect("Failed to deserialize cbor");
+            assert_eq!(command, deserialized);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Byte length must be multiple of 3")]
+    fn bincode_wrong_byte_count() {
+        let serialized = bincode::serialize(&Command::Stream(vec![127_u8, 0])).unwrap();
+        let _: Command<Plh> = bincode::deserialize(&serialized).unwrap();
+    }
+
+    #[test]
+    fn colour_notations_de() {
+        let command: Command<Plh> = Command::Constant {
+            led_count: 1,
+            colour: (0, 255, 0),
+        };
+        for colour in [
+            "[0,255,0]",
+            "\"#00ff00\"",
+            "\"00FF00\"",
+            "\"#0f0\"",
+            "\"lime\"",
+            "{\"r\":0,\"g\":255,\"b\":0}",
+        ] {
+            let as_str = format!(
+                "{{\"type\":\"constant\",\"led_count\":1,\"colour\":{}}}",
+                colour
+            );
+            let deserialized: Command<Plh> = serde_json::from_str(&as_str)
+                .unwrap_or_else(|e| panic!("Failed to deserialize {}: {}", colour, e));
+            assert_eq!(command, deserialized);
+        }
+    }
+
+    #[test]
+    fn pulse_named_colours_de() {
+        let command: Command<Plh> = Command::Pulse {
+            led_count: 5,
+            start: (0, 0, 0),
+            end: (255, 0, 0),
+            frames: 60,
+            period: 2000,
+        };
+        let as_str = "{\"type\":\"pulse\",\"led_count\":5,\"start\":\"black\",\"end\":{\"r\":255,\"g\":0,\"b\":0},\"frames\":60,\"period\":2000}";
+        let deserialized: Command<Plh> =
+            serde_json::from_str(as_str).expect("Failed to deserialize pulse example");
+        assert_eq!(command, deserialized);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value")]
+    fn unknown_colour_name() {
+        let _: Command<Plh> =
+            serde_json::from_str("{\"type\":\"constant\",\"led_count\":1,\"colour\":\"blurple\"}")
+                .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid length")]
+    fn colour_too_long() {
+        let _: Command<Plh> =
+            serde_json::from_str("{\"type\":\"constant\",\"led_count\":1,\"colour\":[0,1,2,3]}")
+                .unwrap();
+    }
+
+    #[test]
+    fn hex_colour_ser() {
+        let options = Options {
+            colour: ColourFormat::Hex,
+            ..Default::default()
+        };
+        let command: Command<Plh> = Command::Pulse {
+            led_count: 5,
+            start: (0, 0, 0),
+            end: (127, 0, 127),
+            frames: 60,
+            period: 2000,
+        };
+        let serialized = serde_json::to_string(&command.serialize_with(options)).unwrap();
+        let as_string = "{\"type\":\"pulse\",\"led_count\":5,\"start\":\"#000000\",\"end\":\"#7f007f\",\"frames\":60,\"period\":2000}";
+        assert_eq!(as_string, serialized);
+        let deserialized: Command<Plh> = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(command, deserialized);
+    }
+
+    #[test]
+    fn hex_colour_ignored_by_binary_formats() {
+        let options = Options {
+            colour: ColourFormat::Hex,
+            ..Default::default()
+        };
+        let command: Command<Plh> = Command::Constant {
+            led_count: 1,
+            colour: (0, 126, 0),
+        };
+        let with_options = bincode::serialize(&command.serialize_with(options)).unwrap();
+        assert_eq!(with_options, bincode::serialize(&command).unwrap());
+    }
+
+    #[test]
+    fn stream_text_bytes_ser() {
+        let command = Command::Stream([0_u8, 127, 255].as_ref());
+        let as_hex = command.serialize_with(Options {
+            bytes: BytesFormat::Hex,
+            ..Default::default()
+        });
+        let as_base64 = command.serialize_with(Options {
+            bytes: BytesFormat::Base64,
+            ..Default::default()
+        });
+        assert_eq!(
+            serde_json::to_string(&as_hex).unwrap(),
+            "{\"type\":\"stream\",\"bytes\":\"hex:007fff\"}"
+        );
+        assert_eq!(
+            serde_json::to_string(&as_base64).unwrap(),
+            "{\"type\":\"stream\",\"bytes\":\"base64:AH//\"}"
+        );
+    }
+
+    #[test]
+    fn stream_text_bytes_de() {
+        let command = Command::Stream(vec![0, 127, 255]);
+        for bytes in [
+            "[0,127,255]",
+            "\"hex:007fff\"",
+            "\"hex:007FFF\"",
+            "\"base64:AH//\"",
+            "\"AH//\"",
+        ] {
+            let as_str = format!("{{\"type\":\"stream\",\"bytes\":{}}}", bytes);
+            let deserialized: Command<Plh> = serde_json::from_str(&as_str)
+                .unwrap_or_else(|e| panic!("Failed to deserialize {}: {}", bytes, e));
+            assert_eq!(command, deserialized);
+        }
+    }
+
+    #[test]
+    fn stream_unprefixed_text_bytes_are_base64() {
+        // Standard base64 of 3 black pixels, also valid hex
+        for bytes in [
+            "AAAAAAAAAAAA",
+            "base64:AAAAAAAAAAAA",
+            "hex:000000000000000000",
+        ] {
+            let as_str = format!("{{\"type\":\"stream\",\"bytes\":\"{}\"}}", bytes);
+            let deserialized: Command<Plh> = serde_json::from_str(&as_str).unwrap();
+            assert_eq!(deserialized, Command::Stream(vec![0; 9]));
+        }
+        // Bare hex digits decode as base64, 4 bytes are not whole pixels
+        let outcome =
+            serde_json::from_str::<Command<Plh>>("{\"type\":\"stream\",\"bytes\":\"007fff\"}");
+        assert!(outcome.is_err());
+    }
+
+    #[test]
+    fn stream_base64_round_trip() {
+        let options = Options {
+            bytes: BytesFormat::Base64,
+            ..Default::default()
+        };
+        // Black frames come out as "AAAAAAAAAAAA", which read as base64 with or without the prefix
+        for bytes in [vec![0; 9], vec![0; 300], (0..=254).collect::<Vec<u8>>()] {
+            let command = Command::Stream(bytes);
+            let serialized = serde_json::to_string(&command.serialize_with(options)).unwrap();
+            let deserialized: Command<Plh> = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(command, deserialized);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Byte length must be multiple of 3")]
+    fn text_bytes_wrong_byte_count() {
+        let _: Command<Plh> =
+            serde_json::from_str("{\"type\": \"stream\", \"bytes\": \"AH8=\"}").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value")]
+    fn text_bytes_garbled() {
+        let _: Command<Plh> =
+            serde_json::from_str("{\"type\": \"stream\", \"bytes\": \"not base64!\"}").unwrap();
+    }
+
+    fn native(command: &Command<Plh>) -> Vec<u8> {
+        let mut buf = vec![0_u8; command.size_in_bytes()];
+        command.try_write_bytes(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn native_seq_de() {
+        for command in binary_cases() {
+            let as_str = serde_json::to_string(&native(&command)).unwrap();
+            let deserialized: Command<Plh> = serde_json::from_str(&as_str)
+                .unwrap_or_else(|e| panic!("Failed to deserialize {}: {}", as_str, e));
+            assert_eq!(command, deserialized);
+        }
+    }
+
+    #[test]
+    fn native_bytes_bincode_borrows() {
+        let command = Command::Stream(vec![0, 127, 0]);
+        let serialized = bincode::serialize(&native(&command)).unwrap();
+        let deserialized: Native<&[u8]> = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized.0, Command::Stream([0, 127, 0].as_ref()));
+        match deserialized.0 {
+            Command::Stream(bytes) => assert_eq!(bytes.as_ptr(), serialized[9..].as_ptr()),
+            _ => panic!("Expected stream"),
+        }
+    }
+
+    #[test]
+    fn native_bytes_e2e() {
+        for command in binary_cases() {
+            let serialized = bincode::serialize(&native(&command)).unwrap();
+            let deserialized: Native<Plh> = bincode::deserialize(&serialized).unwrap();
+            assert_eq!(command, deserialized.0);
+
+            // CBOR byte string, major type 2
+            let bytes = native(&command);
+            let mut serialized = vec![0x58, bytes.len() as u8];
+            serialized.extend_from_slice(&bytes);
+            let deserialized: Native<Plh> =
+                ciborium::de::from_reader(serialized.as_slice()).unwrap();
+            assert_eq!(command, deserialized.0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "CP")]
+    fn native_truncated() {
+        let _: Command<Plh> = serde_json::from_str("[99,1]").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "CH")]
+    fn native_invalid_header() {
+        let serialized = bincode::serialize(&vec![b'x', 0, 0]).unwrap();
+        let _: Native<Plh> = bincode::deserialize(&serialized).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid length")]
+    fn native_seq_too_long() {
+        let _: Command<Plh> = serde_json::from_str("[104,0,0,0,0,0,0,0,0,0,0,0,0]").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "missing field `type`")]
+    fn strict_requires_type() {
+        let _: Command<Plh> = serde_json::from_str("{\"led_count\": 5}").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "missing field `type`")]
+    fn strict_rejects_empty() {
+        let _: Command<Plh> = serde_json::from_str("{}").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown field `frames`")]
+    fn strict_rejects_unused_fields() {
+        let _: Command<Plh> = serde_json::from_str(
+            "{\"type\":\"constant\",\"led_count\":1,\"colour\":[0,126,0],\"frames\":60}",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown field `frames`")]
+    fn strict_rejects_unused_null_fields() {
+        let _: Command<Plh> = serde_json::from_str(
+            "{\"type\":\"constant\",\"led_count\":1,\"colour\":[0,126,0],\"frames\":null}",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown field `bytes`")]
+    fn strict_rejects_health_with_fields() {
+        let _: Command<Plh> =
+            serde_json::from_str("{\"type\":\"health\",\"bytes\":[0,0,0]}").unwrap();
+    }
+
+    #[test]
+    fn lenient_defaults_to_health() {
+        for as_str in ["{}", "{\"led_count\": 5}"] {
+            let deserialized: Lenient<Plh> = serde_json::from_str(as_str).unwrap();
+            assert_eq!(deserialized.0, Command::Health);
+        }
+    }
+
+    #[test]
+    fn lenient_ignores_unused_fields() {
+        let command: Command<Plh> = Command::Constant {
+            led_count: 1,
+            colour: (0, 126, 0),
+        };
+        let as_str = "{\"type\":\"constant\",\"led_count\":1,\"colour\":[0,126,0],\"frames\":60}";
+        let deserialized: Lenient<Plh> = serde_json::from_str(as_str).unwrap();
+        assert_eq!(deserialized.0, command);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown field `not`")]
+    fn lenient_rejects_unknown_fields() {
+        let _: Lenient<Plh> = serde_json::from_str("{\"not\":\"legit\"}").unwrap();
+    }
+
+    #[test]
+    fn lenient_binary_unaffected() {
+        for command in binary_cases() {
+            let serialized = bincode::serialize(&command).unwrap();
+            let deserialized: Lenient<Plh> = bincode::deserialize(&serialized).unwrap();
+            assert_eq!(command, deserialized.0);
+        }
+    }
 }
@@ -0,0 +1,214 @@
+//! Textual encodings of `Command::Stream` payload used by the human readable serde format.
+//!
+//! Strings prefixed with `hex:` are hex, those prefixed with `base64:` are base64 with optional padding.
+//! Unprefixed strings are always base64, as standard encoders emit them, so hex needs its prefix:
+//! `"007fff"` decodes as base64 even though every character is a hex digit.
+use core::fmt::{Display, Formatter, Result as FMTResult, Write};
+
+pub const HEX_PREFIX: &str = "hex:";
+pub const BASE64_PREFIX: &str = "base64:";
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+fn base64_value(digit: u8) -> Option<u8> {
+    match digit {
+        b'A'..=b'Z' => Some(digit - b'A'),
+        b'a'..=b'z' => Some(digit - b'a' + 26),
+        b'0'..=b'9' => Some(digit - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn is_hex(text: &str) -> bool {
+    text.bytes().all(|digit| digit.is_ascii_hexdigit())
+}
+
+/// Payload formatted as lowercase hex
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        for byte in self.0 {
+            f.write_char(HEX_DIGITS[(byte >> 4) as usize] as char)?;
+            f.write_char(HEX_DIGITS[(byte & 0x0f) as usize] as char)?;
+        }
+        Ok(())
+    }
+}
+
+/// Payload formatted as padded standard base64
+pub struct Base64<'a>(pub &'a [u8]);
+
+impl Display for Base64<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        for chunk in self.0.chunks(3) {
+            let mut block = [0_u8; 3];
+            block[..chunk.len()].copy_from_slice(chunk);
+            let bits = u32::from(block[0]) << 16 | u32::from(block[1]) << 8 | u32::from(block[2]);
+            for idx in 0..4 {
+                if idx <= chunk.len() {
+                    let sextet = (bits >> (18 - idx * 6)) & 0x3f;
+                    f.write_char(BASE64_ALPHABET[sextet as usize] as char)?;
+                } else {
+                    f.write_char('=')?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lazily decoded hex or base64 payload, validated on construction
+pub enum Decoded<'a> {
+    Hex(&'a [u8]),
+    Base64 {
+        digits: &'a [u8],
+        bits: u32,
+        pending: u8,
+    },
+}
+
+impl<'a> Decoded<'a> {
+    /// `None` for invalid strings
+    pub fn new(text: &'a str) -> Option<Self> {
+        if let Some(digits) = text.strip_prefix(HEX_PREFIX) {
+            return (digits.len() % 2 == 0 && is_hex(digits))
+                .then_some(Decoded::Hex(digits.as_bytes()));
+        }
+        let text = text.strip_prefix(BASE64_PREFIX).unwrap_or(text);
+
+        let digits = text.trim_end_matches('=').as_bytes();
+        let padding = text.len() - digits.len();
+        let valid = digits.len() % 4 != 1
+            && padding <= 2
+            && (padding == 0 || text.len() % 4 == 0)
+            && digits.iter().all(|digit| base64_value(*digit).is_some());
+        if valid {
+            Some(Decoded::Base64 {
+                digits,
+                bits: 0,
+                pending: 0,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        match self {
+            Decoded::Hex(digits) => digits.len() / 2,
+            Decoded::Base64 {
+                digits, pending, ..
+            } => (digits.len() * 6 + *pending as usize) / 8,
+        }
+    }
+}
+
+impl Iterator for Decoded<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        match self {
+            Decoded::Hex(digits) => {
+                let (pair, rest) = (digits.get(..2)?, &digits[2..]);
+                *digits = rest;
+                Some(hex_value(pair[0])? << 4 | hex_value(pair[1])?)
+            }
+            Decoded::Base64 {
+                digits,
+                bits,
+                pending,
+            } => {
+                while *pending < 8 {
+                    let (digit, rest) = digits.split_first()?;
+                    *digits = rest;
+                    *bits = *bits << 6 | u32::from(base64_value(*digit)?);
+                    *pending += 6;
+                }
+                *pending -= 8;
+                Some((*bits >> *pending) as u8)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Base64, Decoded, Hex};
+    extern crate std;
+    use std::{string::ToString, vec, vec::Vec};
+
+    fn decode(text: &str) -> Option<Vec<u8>> {
+        Decoded::new(text).map(|decoded| decoded.collect())
+    }
+
+    #[test]
+    fn formats_hex_and_base64() {
+        assert_eq!(Hex(&[0, 127, 255]).to_string(), "007fff");
+        assert_eq!(Base64(&[0, 127, 255]).to_string(), "AH//");
+        assert_eq!(Base64(&[1, 2, 3, 4]).to_string(), "AQIDBA==");
+        assert_eq!(Base64(&[1, 2, 3, 4, 5]).to_string(), "AQIDBAU=");
+    }
+
+    #[test]
+    fn given_hex_decodes() {
+        assert_eq!(decode("hex:007fff"), Some(vec![0, 127, 255]));
+        assert_eq!(decode("hex:007FFF"), Some(vec![0, 127, 255]));
+        assert_eq!(decode("hex:"), Some(vec![]));
+    }
+
+    #[test]
+    fn given_base64_decodes() {
+        assert_eq!(decode("AH//"), Some(vec![0, 127, 255]));
+        assert_eq!(decode("AQIDBA=="), Some(vec![1, 2, 3, 4]));
+        assert_eq!(decode("AQIDBA"), Some(vec![1, 2, 3, 4]));
+        assert_eq!(decode("AH/"), Some(vec![0, 127]));
+        assert_eq!(decode("base64:AQIDBA=="), Some(vec![1, 2, 3, 4]));
+        assert_eq!(decode(""), Some(vec![]));
+    }
+
+    #[test]
+    fn given_external_base64_decodes() {
+        // Produced by `base64` from GNU coreutils, 3 pixels of non hex looking base64
+        assert_eq!(
+            decode("/wAAAP8AAAD/"),
+            Some(vec![255, 0, 0, 0, 255, 0, 0, 0, 255])
+        );
+        // 3 black pixels, every character is a hex digit too
+        assert_eq!(decode("AAAAAAAAAAAA"), Some(vec![0; 9]));
+        assert_eq!(decode("base64:AAAAAAAAAAAA"), Some(vec![0; 9]));
+        // Unprefixed hex is base64 as well
+        assert_eq!(decode("007fff"), Some(vec![211, 78, 223, 125]));
+    }
+
+    #[test]
+    fn given_garbage_returns_none() {
+        assert_eq!(decode("A"), None);
+        assert_eq!(decode("AH!/"), None);
+        assert_eq!(decode("AQ==="), None);
+        assert_eq!(decode("hex:007ff"), None);
+        assert_eq!(decode("hex:AH//"), None);
+        assert_eq!(decode("base64:AH!/"), None);
+    }
+
+    #[test]
+    fn size_hint_is_exact() {
+        for text in ["hex:007fff", "AH//", "base64:AQIDBA==", "AQIDBAU"] {
+            let decoded = Decoded::new(text).unwrap();
+            let hint = decoded.size_hint().0;
+            assert_eq!(hint, decoded.count(), "{}", text);
+        }
+    }
+}
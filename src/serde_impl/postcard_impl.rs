@@ -0,0 +1,136 @@
+#![cfg(feature = "postcard_impl")]
+use crate::{Command, CommandError};
+
+fn into_command_error(error: postcard::Error) -> CommandError {
+    match error {
+        postcard::Error::SerializeBufferFull => CommandError::BufferTooSmall,
+        _ => CommandError::MalformedPayload,
+    }
+}
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Writes postcard encoded command into `buf`
+    /// Returns number of bytes written
+    pub fn try_write_postcard(&self, buf: &mut dyn AsMut<[u8]>) -> Result<usize, CommandError> {
+        postcard::to_slice(self, buf.as_mut())
+            .map(|written| written.len())
+            .map_err(into_command_error)
+    }
+}
+
+impl<'a> Command<&'a [u8]> {
+    /// Reads postcard encoded command, `Stream` payload is borrowed from `bytes`
+    pub fn try_read_postcard(bytes: &'a [u8]) -> Result<Self, CommandError> {
+        postcard::from_bytes(bytes).map_err(into_command_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Command, CommandError};
+
+    fn sizes(cmd: &Command<&[u8]>) -> (usize, usize) {
+        let mut native = [0_u8; 128];
+        let mut compact = [0_u8; 128];
+        let native = cmd
+            .try_write_bytes(&mut native)
+            .expect("Native encoding fail");
+        let compact = cmd
+            .try_write_postcard(&mut compact)
+            .expect("Postcard encoding fail");
+        (native, compact)
+    }
+
+    fn round_trip(cmd: Command<&[u8]>) {
+        let mut buf = [0_u8; 128];
+        let len = cmd
+            .try_write_postcard(&mut buf)
+            .expect("Postcard encoding fail");
+        let decoded = Command::try_read_postcard(&buf[..len]).expect("Postcard decoding fail");
+        assert_eq!(cmd, decoded);
+    }
+
+    #[test]
+    fn e2e_postcard() {
+        round_trip(Command::Health);
+        round_trip(Command::Constant {
+            led_count: 300,
+            colour: (254, 0, 254),
+        });
+        round_trip(Command::Stream(&[127, 127, 127, 0, 0, 0]));
+        round_trip(Command::Pulse {
+            led_count: 300,
+            start: (0, 0, 0),
+            end: (255, 0, 0),
+            frames: 60,
+            period: 1000,
+        });
+    }
+
+    #[test]
+    fn postcard_stream_borrows_payload() {
+        let mut buf = [0_u8; 16];
+        let cmd = Command::Stream([1, 2, 3].as_ref());
+        let len = cmd.try_write_postcard(&mut buf).unwrap();
+        let decoded = Command::try_read_postcard(&buf[..len]).unwrap();
+        match decoded {
+            Command::Stream(bytes) => assert_eq!(bytes.as_ptr(), buf[2..].as_ptr()),
+            _ => panic!("Expected stream"),
+        }
+    }
+
+    #[test]
+    fn postcard_size_vs_native() {
+        // Variant index and led_count/period varints replace the header byte and big endian u16s
+        assert_eq!(sizes(&Command::Health), (1, 1));
+        assert_eq!(
+            sizes(&Command::Constant {
+                led_count: 60,
+                colour: (255, 0, 0),
+            }),
+            (6, 5)
+        );
+        assert_eq!(
+            sizes(&Command::Constant {
+                led_count: 300,
+                colour: (255, 0, 0),
+            }),
+            (6, 6)
+        );
+        assert_eq!(
+            sizes(&Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            }),
+            (12, 12)
+        );
+        // Payload length prefix is the only overhead for streams
+        assert_eq!(sizes(&Command::Stream(&[0; 6])), (7, 8));
+        assert_eq!(sizes(&Command::Stream(&[0; 120])), (121, 122));
+    }
+
+    #[test]
+    fn given_buf_too_small_fails_to_write_postcard() {
+        let mut buf = [0_u8; 3];
+        let outcome = Command::Stream([0_u8; 6].as_ref()).try_write_postcard(&mut buf);
+        assert_eq!(outcome.unwrap_err(), CommandError::BufferTooSmall);
+    }
+
+    #[test]
+    fn given_invalid_postcard_returns_error() {
+        assert_eq!(
+            Command::try_read_postcard(&[7]).unwrap_err(),
+            CommandError::MalformedPayload
+        );
+        assert_eq!(
+            Command::try_read_postcard(&[2, 2, 0, 0]).unwrap_err(),
+            CommandError::MalformedPayload
+        );
+    }
+}
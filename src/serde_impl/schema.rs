@@ -0,0 +1,134 @@
+#![cfg(feature = "schema")]
+//! Definitions of the human readable format for consumers outside Rust.
+
+/// JSON Schema (draft 7) of JSON produced by `ser` and accepted by `de`
+pub const JSON_SCHEMA: &str = include_str!("command.schema.json");
+
+/// TypeScript definitions of the same format
+pub const TYPESCRIPT: &str = include_str!("command.d.ts");
+
+#[cfg(test)]
+mod tests {
+    use super::{JSON_SCHEMA, TYPESCRIPT};
+    use crate::{
+        ser::{BytesFormat, ColourFormat, Options},
+        serde_impl::colour::NAMED_COLOURS,
+        Command,
+    };
+    use jsonschema::JSONSchema;
+    use serde_json::{json, Value};
+    extern crate std;
+    use std::{collections::BTreeSet, format, vec, vec::Vec};
+
+    fn schema() -> JSONSchema {
+        let schema: Value = serde_json::from_str(JSON_SCHEMA).expect("Schema is not JSON");
+        JSONSchema::compile(&schema).expect("Schema does not compile")
+    }
+
+    fn commands() -> Vec<Command<Vec<u8>>> {
+        vec![
+            Command::Health,
+            Command::Constant {
+                led_count: 60,
+                colour: (255, 0, 0),
+            },
+            Command::Stream(vec![0; 9]),
+            Command::Stream((0..=254).collect()),
+            Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (127, 0, 127),
+                frames: 60,
+                period: 1000,
+            },
+        ]
+    }
+
+    #[test]
+    fn serializer_output_matches_schema() {
+        let schema = schema();
+        for colour in [ColourFormat::Array, ColourFormat::Hex] {
+            for bytes in [BytesFormat::Array, BytesFormat::Hex, BytesFormat::Base64] {
+                let options = Options { colour, bytes };
+                for command in commands() {
+                    let value = serde_json::to_value(command.serialize_with(options)).unwrap();
+                    assert!(schema.is_valid(&value), "{} does not match schema", value);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn schema_rejects_what_deserializer_rejects() {
+        let schema = schema();
+        for value in [
+            json!({}),
+            json!({"led_count": 5}),
+            json!({"type": "constant", "led_count": 1, "colour": [0, 126, 0], "frames": 60}),
+            json!({"type": "constant", "led_count": 1, "colour": "blurple"}),
+            json!({"type": "constant", "led_count": 70000, "colour": [0, 0, 0]}),
+            json!({"type": "stream", "bytes": "not base64!"}),
+            json!({"type": "stream", "bytes": "hex:007ff"}),
+            json!({"type": "strobe"}),
+        ] {
+            assert!(!schema.is_valid(&value), "{} matches schema", value);
+            assert!(serde_json::from_value::<Command<Vec<u8>>>(value).is_err());
+        }
+    }
+
+    #[test]
+    fn schema_accepts_alternative_notations() {
+        let schema = schema();
+        for value in [
+            json!({"type": "constant", "led_count": 1, "colour": "rebeccapurple"}),
+            json!({"type": "constant", "led_count": 1, "colour": "#0f0"}),
+            json!({"type": "constant", "led_count": 1, "colour": {"r": 0, "g": 255, "b": 0}}),
+            json!({"type": "stream", "bytes": "AH//"}),
+            json!({"type": "stream", "bytes": "hex:007fff"}),
+            json!({"type": "stream", "bytes": "AAAAAAAAAAAA"}),
+            json!({"type": "stream", "bytes": "base64:AAAAAAAAAAAA"}),
+        ] {
+            assert!(schema.is_valid(&value), "{} does not match schema", value);
+            assert!(serde_json::from_value::<Command<Vec<u8>>>(value).is_ok());
+        }
+    }
+
+    #[test]
+    fn colour_names_match_parser() {
+        let parser: BTreeSet<_> = NAMED_COLOURS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(parser.len(), NAMED_COLOURS.len(), "Duplicate colour names");
+
+        let schema: Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+        let in_schema: BTreeSet<_> = schema["definitions"]["colour"]["anyOf"][2]["enum"]
+            .as_array()
+            .expect("Colour names missing from schema")
+            .iter()
+            .map(|name| name.as_str().unwrap())
+            .collect();
+        assert_eq!(in_schema, parser);
+
+        let (_, union) = TYPESCRIPT
+            .split_once("export type ColourName =")
+            .expect("ColourName missing from TypeScript");
+        let (union, _) = union.split_once(';').unwrap();
+        let in_typescript: BTreeSet<_> = union
+            .split('|')
+            .map(|name| name.trim().trim_matches('"'))
+            .filter(|name| !name.is_empty())
+            .collect();
+        assert_eq!(in_typescript, parser);
+    }
+
+    #[test]
+    fn typescript_covers_variants() {
+        let schema: Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+        for variant in ["health", "constant", "stream", "pulse"] {
+            let definition = &schema["definitions"][variant];
+            assert!(TYPESCRIPT.contains(&format!("type: \"{}\";", variant)));
+            for field in definition["required"].as_array().unwrap() {
+                let field = field.as_str().unwrap();
+                assert!(TYPESCRIPT.contains(&format!("  {}: ", field)), "{}", field);
+            }
+        }
+    }
+}
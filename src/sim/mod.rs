@@ -0,0 +1,335 @@
+//! What a strip displays for the commands it received, shared by firmware and host side previews.
+//!
+//! Time is given in milliseconds by the caller, so the same code runs off a hardware timer or a virtual clock.
+//! `Constant` and `Stream` light the LEDs they cover and turn the rest off, `Health` changes nothing.
+//! `Pulse` fades its LEDs from `start` to `end` and back once every `period` ms in `frames` steps,
+//! repeating until the next command.
+use crate::{Command, Pixels};
+use core::fmt::{Display, Formatter, Result as FMTResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pulse {
+    led_count: u16,
+    start: (u8, u8, u8),
+    end: (u8, u8, u8),
+    frames: u8,
+    period: u16,
+    started_at: u32,
+}
+
+impl Pulse {
+    /// Step within the current pulse at `now`
+    fn frame(&self, now: u32) -> u32 {
+        if self.frames == 0 || self.period == 0 {
+            return 0;
+        }
+        let elapsed = now.wrapping_sub(self.started_at) % self.period as u32;
+        elapsed * self.frames as u32 / self.period as u32
+    }
+
+    /// Time the step following the one at `now` starts
+    fn next_frame_at(&self, now: u32) -> u32 {
+        let frames = self.frames.max(1) as u32;
+        let elapsed = now.wrapping_sub(self.started_at);
+        let period = self.period.max(1) as u32;
+        let cycle = elapsed - elapsed % period;
+        let next = self.frame(now) + 1;
+        // Ceiling, so the next step really has begun at the returned time
+        let offset = (next * period).div_ceil(frames);
+        self.started_at.wrapping_add(cycle).wrapping_add(offset)
+    }
+
+    fn colour(&self, now: u32) -> (u8, u8, u8) {
+        let frames = self.frames as u32;
+        if frames == 0 {
+            return self.start;
+        }
+        let frame = self.frame(now);
+        // Triangle from 0 at the first step to 255 half way through and back
+        let distance = (2 * frame).abs_diff(frames);
+        let level = 255 * (frames - distance.min(frames)) / frames;
+        let mix = |from: u8, to: u8| {
+            let (from, to) = (from as u32, to as u32);
+            ((from * (255 - level) + to * level + 127) / 255) as u8
+        };
+        (
+            mix(self.start.0, self.end.0),
+            mix(self.start.1, self.end.1),
+            mix(self.start.2, self.end.2),
+        )
+    }
+}
+
+/// LED colours of a strip driven by commands, stored as `[r, g, b]` triplets in `B`
+#[derive(Debug, Clone)]
+pub struct Strip<B> {
+    leds: B,
+    pulse: Option<Pulse>,
+}
+
+impl<B> Strip<B>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Strip of `leds.len() / 3` LEDs, all of them off
+    pub fn new(leds: B) -> Self {
+        let mut strip = Self { leds, pulse: None };
+        strip.clear();
+        strip
+    }
+
+    /// Turns every LED off and stops a running `Pulse`
+    pub fn clear(&mut self) {
+        self.leds
+            .as_mut()
+            .iter_mut()
+            .for_each(|channel| *channel = 0);
+        self.pulse = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.leds.as_ref().len() / 3
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn fill(&mut self, count: usize, colour: (u8, u8, u8)) {
+        let count = count.min(self.len());
+        for (idx, rgb) in self.leds.as_mut().chunks_exact_mut(3).enumerate() {
+            let colour = if idx < count { colour } else { (0, 0, 0) };
+            rgb.copy_from_slice(&[colour.0, colour.1, colour.2]);
+        }
+    }
+
+    /// Applies `command` received at `now` ms. Payload beyond the strip is ignored.
+    pub fn apply<T: AsRef<[u8]>>(&mut self, command: &Command<T>, now: u32) {
+        match *command {
+            Command::Health => return,
+            Command::Constant { led_count, colour } => self.fill(led_count as usize, colour),
+            Command::Stream(ref bytes) => {
+                let bytes = bytes.as_ref();
+                let leds = self.leds.as_mut();
+                let len = bytes.len().min(leds.len()) / 3 * 3;
+                leds[..len].copy_from_slice(&bytes[..len]);
+                leds[len..].iter_mut().for_each(|channel| *channel = 0);
+            }
+            Command::Pulse {
+                led_count,
+                start,
+                end,
+                frames,
+                period,
+            } => {
+                self.pulse = Some(Pulse {
+                    led_count,
+                    start,
+                    end,
+                    frames,
+                    period,
+                    started_at: now,
+                });
+                self.render(now);
+                return;
+            }
+        }
+        self.pulse = None;
+    }
+
+    /// Brings a running `Pulse` to its state at `now`, returns `false` when nothing is animating
+    pub fn render(&mut self, now: u32) -> bool {
+        match self.pulse {
+            Some(pulse) => {
+                self.fill(pulse.led_count as usize, pulse.colour(now));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// When the colours change next without a new command, `None` when they stay as they are
+    pub fn next_frame_at(&self, now: u32) -> Option<u32> {
+        self.pulse
+            .filter(|pulse| pulse.frames > 1)
+            .map(|pulse| pulse.next_frame_at(now))
+    }
+
+    pub fn pixels(&self) -> Pixels<'_> {
+        Pixels::new(self.leds.as_ref())
+    }
+
+    /// Raw `[r, g, b]` bytes, same layout as a `Stream` payload
+    pub fn as_bytes(&self) -> &[u8] {
+        self.leds.as_ref()
+    }
+
+    /// Formats LEDs as truecolor ANSI blocks
+    pub fn ansi(&self) -> Ansi<'_> {
+        Ansi(self.pixels())
+    }
+}
+
+/// Truecolor ANSI rendering of a strip, one block per LED, see [`Strip::ansi`]
+#[derive(Debug, Clone)]
+pub struct Ansi<'a>(Pixels<'a>);
+
+impl Display for Ansi<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        for (r, g, b) in self.0.clone() {
+            write!(f, "\x1b[38;2;{};{};{}m\u{2588}", r, g, b)?;
+        }
+        f.write_str("\x1b[0m")
+    }
+}
+
+/// Commands stamped with the millisecond they arrive at, replayed against a virtual clock.
+/// Rendering only depends on the commands and the requested time, so previews and tests are deterministic.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeline<'a, T> {
+    commands: &'a [(u32, Command<T>)],
+}
+
+impl<'a, T> Timeline<'a, T>
+where
+    T: AsRef<[u8]>,
+{
+    /// `commands` must be sorted by time
+    pub fn new(commands: &'a [(u32, Command<T>)]) -> Self {
+        debug_assert!(
+            commands.windows(2).all(|pair| pair[0].0 <= pair[1].0),
+            "commands must be sorted by time"
+        );
+        Self { commands }
+    }
+
+    /// Time of the last command, the strip only changes afterwards while a `Pulse` runs
+    pub fn end(&self) -> u32 {
+        self.commands.last().map_or(0, |(time, _)| *time)
+    }
+
+    /// Sets `strip` to what it displays at `at` ms, starting from all LEDs off at time 0
+    pub fn render_at<B>(&self, at: u32, strip: &mut Strip<B>)
+    where
+        B: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        strip.clear();
+        for (time, command) in self.commands.iter().take_while(|(time, _)| *time <= at) {
+            strip.apply(command, *time);
+        }
+        strip.render(at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Strip, Timeline};
+    use crate::Command;
+    extern crate std;
+    use std::{string::ToString, vec::Vec};
+
+    fn pulse() -> Command<&'static [u8]> {
+        Command::Pulse {
+            led_count: 2,
+            start: (0, 0, 0),
+            end: (255, 100, 0),
+            frames: 4,
+            period: 1000,
+        }
+    }
+
+    #[test]
+    fn constant_lights_covered_leds() {
+        let mut strip = Strip::new([7_u8; 9]);
+        strip.apply(
+            &Command::<&[u8]>::Constant {
+                led_count: 2,
+                colour: (1, 2, 3),
+            },
+            0,
+        );
+        assert_eq!(strip.as_bytes(), &[1, 2, 3, 1, 2, 3, 0, 0, 0]);
+        assert_eq!(strip.next_frame_at(0), None);
+    }
+
+    #[test]
+    fn stream_is_clipped_to_strip() {
+        let mut strip = Strip::new([0_u8; 6]);
+        strip.apply(&Command::Stream(&[1_u8, 2, 3, 4, 5, 6, 7, 8, 9][..]), 0);
+        assert_eq!(strip.as_bytes(), &[1, 2, 3, 4, 5, 6]);
+        strip.apply(&Command::Stream(&[9_u8, 9, 9][..]), 0);
+        assert_eq!(strip.as_bytes(), &[9, 9, 9, 0, 0, 0]);
+        strip.apply(&Command::<&[u8]>::Health, 0);
+        assert_eq!(strip.as_bytes(), &[9, 9, 9, 0, 0, 0]);
+    }
+
+    #[test]
+    fn pulse_fades_there_and_back() {
+        let mut strip = Strip::new([0_u8; 9]);
+        strip.apply(&pulse(), 100);
+        let colours: Vec<_> = [100, 349, 350, 600, 850, 1100]
+            .iter()
+            .map(|now| {
+                strip.render(*now);
+                strip.pixels().next().unwrap()
+            })
+            .collect();
+        assert_eq!(
+            colours,
+            [
+                (0, 0, 0),
+                (0, 0, 0),
+                (127, 50, 0),
+                (255, 100, 0),
+                (127, 50, 0),
+                (0, 0, 0)
+            ]
+        );
+        assert_eq!(strip.pixels().nth(2), Some((0, 0, 0)));
+    }
+
+    #[test]
+    fn pulse_reports_frame_timing() {
+        let mut strip = Strip::new([0_u8; 3]);
+        strip.apply(&pulse(), 100);
+        assert_eq!(strip.next_frame_at(100), Some(350));
+        assert_eq!(strip.next_frame_at(349), Some(350));
+        assert_eq!(strip.next_frame_at(850), Some(1100));
+        assert_eq!(strip.next_frame_at(1100), Some(1350));
+        strip.apply(&Command::Stream(&[1_u8, 2, 3][..]), 1200);
+        assert_eq!(strip.next_frame_at(1200), None);
+        assert!(!strip.render(1300));
+        assert_eq!(strip.as_bytes(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn renders_ansi_blocks() {
+        let mut strip = Strip::new([0_u8; 3]);
+        strip.apply(&Command::Stream(&[255_u8, 0, 16][..]), 0);
+        assert_eq!(
+            strip.ansi().to_string(),
+            "\x1b[38;2;255;0;16m\u{2588}\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn timeline_renders_any_time() {
+        let commands = [
+            (0, Command::Stream(&[1_u8, 2, 3][..])),
+            (100, pulse()),
+            (1100, Command::Stream(&[4_u8, 5, 6][..])),
+        ];
+        let timeline = Timeline::new(&commands);
+        let mut strip = Strip::new([0_u8; 3]);
+        timeline.render_at(50, &mut strip);
+        assert_eq!(strip.as_bytes(), &[1, 2, 3]);
+        timeline.render_at(600, &mut strip);
+        assert_eq!(strip.as_bytes(), &[255, 100, 0]);
+        // Earlier time after a later one gives the same result as rendering it first
+        timeline.render_at(350, &mut strip);
+        assert_eq!(strip.as_bytes(), &[127, 50, 0]);
+        timeline.render_at(5000, &mut strip);
+        assert_eq!(strip.as_bytes(), &[4, 5, 6]);
+        assert_eq!(timeline.end(), 1100);
+    }
+}
@@ -0,0 +1,243 @@
+#![cfg(feature = "sim_export")]
+//! Frames recorded from a [`Timeline`] without a terminal, for golden image tests in CI.
+//!
+//! Raw dumps are the `[r, g, b]` bytes of every frame back to back.
+//! PNG strips hold one row per frame and one pixel per LED.
+extern crate std;
+use crate::{Strip, Timeline};
+use core::fmt::{Display, Formatter, Result as FMTResult};
+use std::{
+    io::{self, Read, Write},
+    vec,
+    vec::Vec,
+};
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Encoding(png::EncodingError),
+    Decoding(png::DecodingError),
+    /// Image is not 8 bit RGB or raw dump is not a whole number of frames
+    Layout,
+}
+
+impl From<io::Error> for ExportError {
+    fn from(value: io::Error) -> Self {
+        ExportError::Io(value)
+    }
+}
+
+impl From<png::EncodingError> for ExportError {
+    fn from(value: png::EncodingError) -> Self {
+        ExportError::Encoding(value)
+    }
+}
+
+impl From<png::DecodingError> for ExportError {
+    fn from(value: png::DecodingError) -> Self {
+        ExportError::Decoding(value)
+    }
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        match self {
+            ExportError::Io(err) => write!(f, "IO error: {}", err),
+            ExportError::Encoding(err) => write!(f, "PNG encoding error: {}", err),
+            ExportError::Decoding(err) => write!(f, "PNG decoding error: {}", err),
+            ExportError::Layout => f.write_str("Not a strip of 8 bit RGB frames"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExportError::Io(err) => Some(err),
+            ExportError::Encoding(err) => Some(err),
+            ExportError::Decoding(err) => Some(err),
+            ExportError::Layout => None,
+        }
+    }
+}
+
+/// Colours of a strip at consecutive points of virtual time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frames {
+    leds: usize,
+    count: usize,
+    bytes: Vec<u8>,
+}
+
+impl Frames {
+    /// Renders `count` frames of a `leds` long strip at `start`, `start + step`, ... ms
+    pub fn record<T>(
+        timeline: &Timeline<'_, T>,
+        leds: usize,
+        start: u32,
+        step: u32,
+        count: usize,
+    ) -> Self
+    where
+        T: AsRef<[u8]>,
+    {
+        let mut strip = Strip::new(vec![0_u8; leds * 3]);
+        let mut bytes = Vec::with_capacity(leds * 3 * count);
+        let mut at = start;
+        for _ in 0..count {
+            timeline.render_at(at, &mut strip);
+            bytes.extend_from_slice(strip.as_bytes());
+            at = at.wrapping_add(step);
+        }
+        Self { leds, count, bytes }
+    }
+
+    pub fn leds(&self) -> usize {
+        self.leds
+    }
+
+    /// Number of frames
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// `[r, g, b]` bytes of the `index`-th frame
+    pub fn frame(&self, index: usize) -> Option<&[u8]> {
+        let size = self.leds * 3;
+        if index < self.count {
+            Some(&self.bytes[index * size..(index + 1) * size])
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[u8]> + '_ {
+        (0..self.count).map(move |index| self.frame(index).unwrap_or_default())
+    }
+
+    pub fn write_raw<W: Write>(&self, mut writer: W) -> Result<(), ExportError> {
+        writer.write_all(&self.bytes)?;
+        Ok(())
+    }
+
+    /// Reads a raw dump of a `leds` long strip
+    pub fn read_raw<R: Read>(leds: usize, mut reader: R) -> Result<Self, ExportError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let size = leds * 3;
+        if size == 0 || bytes.len() % size != 0 {
+            return Err(ExportError::Layout);
+        }
+        Ok(Self {
+            leds,
+            count: bytes.len() / size,
+            bytes,
+        })
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), ExportError> {
+        let mut encoder = png::Encoder::new(writer, self.leds as u32, self.count as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.bytes)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    pub fn read_png<R: Read>(reader: R) -> Result<Self, ExportError> {
+        let mut reader = png::Decoder::new(reader).read_info()?;
+        let mut bytes = vec![0_u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut bytes)?;
+        if info.color_type != png::ColorType::Rgb || info.bit_depth != png::BitDepth::Eight {
+            return Err(ExportError::Layout);
+        }
+        bytes.truncate(info.buffer_size());
+        Ok(Self {
+            leds: info.width as usize,
+            count: info.height as usize,
+            bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportError, Frames};
+    use crate::{Command, Timeline};
+    extern crate std;
+    use std::vec::Vec;
+
+    fn frames() -> Frames {
+        let commands = [
+            (
+                0,
+                Command::Stream(&[255_u8, 0, 0, 0, 255, 0, 0, 0, 255][..]),
+            ),
+            (
+                200,
+                Command::Pulse {
+                    led_count: 2,
+                    start: (0, 0, 0),
+                    end: (255, 255, 255),
+                    frames: 4,
+                    period: 400,
+                },
+            ),
+            (
+                600,
+                Command::Constant {
+                    led_count: 3,
+                    colour: (0, 0, 64),
+                },
+            ),
+        ];
+        Frames::record(&Timeline::new(&commands), 3, 0, 100, 8)
+    }
+
+    #[test]
+    fn records_frames() {
+        let frames = frames();
+        assert_eq!(frames.len(), 8);
+        assert_eq!(
+            frames.frame(1),
+            Some(&[255_u8, 0, 0, 0, 255, 0, 0, 0, 255][..])
+        );
+        assert_eq!(
+            frames.frame(4),
+            Some(&[255_u8, 255, 255, 255, 255, 255, 0, 0, 0][..])
+        );
+        assert_eq!(
+            frames.frame(7),
+            Some(&[0_u8, 0, 64, 0, 0, 64, 0, 0, 64][..])
+        );
+        assert_eq!(frames.frame(8), None);
+        assert_eq!(frames.iter().len(), 8);
+    }
+
+    #[test]
+    fn matches_golden_png() {
+        let golden = Frames::read_png(&include_bytes!("golden_pulse.png")[..]).unwrap();
+        assert_eq!(frames(), golden);
+    }
+
+    #[test]
+    fn round_trips_png_and_raw() {
+        let frames = frames();
+        let mut png = Vec::new();
+        frames.write_png(&mut png).unwrap();
+        assert_eq!(Frames::read_png(&png[..]).unwrap(), frames);
+        let mut raw = Vec::new();
+        frames.write_raw(&mut raw).unwrap();
+        assert_eq!(raw.len(), 8 * 9);
+        assert_eq!(Frames::read_raw(3, &raw[..]).unwrap(), frames);
+        assert!(matches!(
+            Frames::read_raw(5, &raw[..]),
+            Err(ExportError::Layout)
+        ));
+    }
+}
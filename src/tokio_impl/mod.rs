@@ -0,0 +1,241 @@
+#![cfg(feature = "tokio_impl")]
+extern crate std;
+use crate::{
+    framing::{frame_len, frame_prefix, FRAME_PREFIX_LEN},
+    Command, CommandError,
+};
+use bytes::{Buf, BufMut, BytesMut};
+use core::{
+    convert::TryFrom,
+    fmt::{Display, Formatter, Result as FMTResult},
+};
+use std::{io, vec::Vec};
+use tokio_util::codec::{Decoder, Encoder};
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(io::Error),
+    Command(CommandError),
+    /// Encoded command does not fit into the length prefix or exceeds `max_frame_len`
+    FrameTooLarge(usize),
+}
+
+impl From<io::Error> for CodecError {
+    fn from(value: io::Error) -> Self {
+        CodecError::Io(value)
+    }
+}
+
+impl From<CommandError> for CodecError {
+    fn from(value: CommandError) -> Self {
+        CodecError::Command(value)
+    }
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+        match self {
+            CodecError::Io(err) => write!(f, "IO error: {}", err),
+            CodecError::Command(err) => write!(f, "Command error: {}", err),
+            CodecError::FrameTooLarge(len) => write!(f, "Frame of {} bytes is too large", len),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CodecError::Io(err) => Some(err),
+            CodecError::Command(err) => Some(err),
+            CodecError::FrameTooLarge(_) => None,
+        }
+    }
+}
+
+/// Frames commands as big endian u16 length followed by `try_write_bytes` encoding.
+///
+/// Native encoding has no length of its own (`Stream` payload runs to the end of the buffer),
+/// so the prefix is what lets commands share a byte stream.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandCodec {
+    max_frame_len: usize,
+}
+
+impl CommandCodec {
+    pub fn new() -> Self {
+        Self {
+            max_frame_len: u16::MAX as usize,
+        }
+    }
+
+    /// Limits size of encoded command, larger frames fail with `CodecError::FrameTooLarge`
+    pub fn with_max_frame_len(max_frame_len: usize) -> Self {
+        Self {
+            max_frame_len: max_frame_len.min(u16::MAX as usize),
+        }
+    }
+
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+}
+
+impl Default for CommandCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for CommandCodec {
+    type Item = Command<Vec<u8>>;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < FRAME_PREFIX_LEN {
+            return Ok(None);
+        }
+        let len = frame_len([src[0], src[1]]);
+        if len > self.max_frame_len {
+            return Err(CodecError::FrameTooLarge(len));
+        }
+        if src.len() < FRAME_PREFIX_LEN + len {
+            src.reserve(FRAME_PREFIX_LEN + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(FRAME_PREFIX_LEN);
+        let frame = src.split_to(len);
+        Ok(Some(Command::try_from(&frame[..])?))
+    }
+}
+
+impl<T> Encoder<Command<T>> for CommandCodec
+where
+    T: AsRef<[u8]>,
+{
+    type Error = CodecError;
+
+    fn encode(&mut self, item: Command<T>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(&item, dst)
+    }
+}
+
+impl<T> Encoder<&Command<T>> for CommandCodec
+where
+    T: AsRef<[u8]>,
+{
+    type Error = CodecError;
+
+    fn encode(&mut self, item: &Command<T>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let len = item.size_in_bytes();
+        let prefix = frame_prefix(len)
+            .filter(|_| len <= self.max_frame_len)
+            .ok_or(CodecError::FrameTooLarge(len))?;
+
+        dst.reserve(FRAME_PREFIX_LEN + len);
+        dst.put_slice(&prefix);
+        let start = dst.len();
+        dst.resize(start + len, 0);
+        item.try_write_bytes(&mut &mut dst[start..])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodecError, CommandCodec};
+    use crate::{Command, CommandError};
+    use bytes::BytesMut;
+    use futures::{SinkExt, StreamExt};
+    use tokio_util::codec::{Decoder, Encoder, Framed};
+    extern crate std;
+    use std::{vec, vec::Vec};
+
+    fn commands() -> Vec<Command<Vec<u8>>> {
+        vec![
+            Command::Health,
+            Command::Constant {
+                led_count: 257,
+                colour: (254, 0, 254),
+            },
+            Command::Stream(vec![127; 900]),
+            Command::Pulse {
+                led_count: 300,
+                start: (0, 0, 0),
+                end: (255, 0, 0),
+                frames: 60,
+                period: 1000,
+            },
+        ]
+    }
+
+    #[test]
+    fn encodes_length_prefix() {
+        let mut buf = BytesMut::new();
+        CommandCodec::new()
+            .encode(Command::Stream([1_u8, 2, 3].as_ref()), &mut buf)
+            .unwrap();
+        assert_eq!(&buf[..], &[0, 4, b's', 1, 2, 3]);
+    }
+
+    #[test]
+    fn given_partial_frame_waits_for_more() {
+        let mut codec = CommandCodec::new();
+        let mut buf = BytesMut::new();
+        codec.encode(&commands()[1], &mut buf).unwrap();
+        let mut partial = buf.split_to(4);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+        partial.unsplit(buf);
+        assert_eq!(
+            codec.decode(&mut partial).unwrap(),
+            Some(commands()[1].clone())
+        );
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    fn given_garbled_frame_returns_error() {
+        let mut codec = CommandCodec::new();
+        let mut buf = BytesMut::from(&[0, 2, b'x', 0, 0, 1, b'h'][..]);
+        match codec.decode(&mut buf) {
+            Err(CodecError::Command(err)) => assert_eq!(err, CommandError::InvalidHeader),
+            other => panic!("Unexpected {:?}", other),
+        }
+        // Broken frame is consumed, the next one still decodes
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(Command::Health));
+    }
+
+    #[test]
+    fn given_frame_too_large_returns_error() {
+        let mut codec = CommandCodec::with_max_frame_len(16);
+        let mut buf = BytesMut::new();
+        let outcome = codec.encode(Command::Stream(vec![0; 18]), &mut buf);
+        assert!(matches!(outcome, Err(CodecError::FrameTooLarge(19))));
+        let mut buf = BytesMut::from(&[0, 19][..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(CodecError::FrameTooLarge(19))
+        ));
+    }
+
+    #[tokio::test]
+    async fn e2e_duplex() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = Framed::new(client, CommandCodec::new());
+        let mut server = Framed::new(server, CommandCodec::new());
+
+        let sender = tokio::spawn(async move {
+            for command in commands() {
+                client.send(command).await.unwrap();
+            }
+        });
+
+        let mut received = Vec::new();
+        while received.len() < commands().len() {
+            received.push(server.next().await.unwrap().unwrap());
+        }
+        sender.await.unwrap();
+        assert_eq!(received, commands());
+        assert!(server.next().await.is_none());
+    }
+}
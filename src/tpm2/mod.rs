@@ -0,0 +1,239 @@
+#![cfg(feature = "tpm2_impl")]
+//! TPM2 serial frames, as sent by Jinx!, Glediator and ambient lighting software.
+//!
+//! A frame is `0xC9`, its type, big endian payload length, the payload and `0x36`.
+//! Data frames carry colours of every LED, command and response frames are skipped by the decoder.
+use crate::{Command, CommandError};
+
+pub const START: u8 = 0xc9;
+pub const END: u8 = 0x36;
+/// Frame type carrying colours
+pub const DATA: u8 = 0xda;
+/// Frame type carrying a device command
+pub const COMMAND: u8 = 0xc0;
+/// Frame type carrying a response to a command
+pub const RESPONSE: u8 = 0xaa;
+pub const HEADER_LEN: usize = 4;
+/// Header and end byte
+pub const OVERHEAD: usize = HEADER_LEN + 1;
+
+impl<T> Command<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Writes `Stream` or `Constant` as TPM2 data frame, returns number of bytes written.
+    ///
+    /// Fails with `InvalidHeader` for `Health` and `Pulse`, which have no TPM2 counterpart,
+    /// `MalformedPayload` for payloads that are not whole pixels and `ValueOutOfRange`
+    /// when colours overflow the `u16` length
+    pub fn try_write_tpm2(&self, buf: &mut [u8]) -> Result<usize, CommandError> {
+        let frame = buf
+            .get_mut(HEADER_LEN..)
+            .ok_or(CommandError::BufferTooSmall)?;
+        let len = self.write_frame(frame)?;
+        if len > u16::MAX as usize {
+            return Err(CommandError::ValueOutOfRange);
+        }
+        *buf.get_mut(HEADER_LEN + len)
+            .ok_or(CommandError::BufferTooSmall)? = END;
+        let [high, low] = (len as u16).to_be_bytes();
+        buf[..HEADER_LEN].copy_from_slice(&[START, DATA, high, low]);
+        Ok(len + OVERHEAD)
+    }
+}
+
+/// Decodes TPM2 data frames byte by byte into `B`, e.g. straight from a UART interrupt
+#[derive(Debug, Clone)]
+pub struct Tpm2Decoder<B> {
+    buf: B,
+    header: [u8; HEADER_LEN],
+    header_len: usize,
+    /// Payload length of the current frame
+    expected: usize,
+    received: usize,
+    /// Current frame is not a data frame or does not fit into `buf`, its payload is dropped
+    skipping: bool,
+}
+
+impl<B> Tpm2Decoder<B>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+{
+    /// Data frames of up to `buf.len()` bytes are decoded
+    pub fn new(buf: B) -> Self {
+        Self {
+            buf,
+            header: [0; HEADER_LEN],
+            header_len: 0,
+            expected: 0,
+            received: 0,
+            skipping: false,
+        }
+    }
+
+    /// Forgets a partially received frame
+    pub fn reset(&mut self) {
+        self.header_len = 0;
+        self.skipping = false;
+    }
+
+    /// Feeds the next byte, returns the `Stream` it completes.
+    ///
+    /// Fails with `InvalidHeader` on an unknown frame type, `BufferTooSmall` when the data frame
+    /// does not fit into the buffer and `MalformedPayload` when it does not end with [`END`].
+    pub fn push(&mut self, byte: u8) -> Result<Option<Command<&[u8]>>, CommandError> {
+        if self.header_len < HEADER_LEN {
+            if self.header_len == 0 && byte != START {
+                return Ok(None);
+            }
+            self.header[self.header_len] = byte;
+            self.header_len += 1;
+            if self.header_len == 2 && ![DATA, COMMAND, RESPONSE].contains(&byte) {
+                self.header_len = usize::from(byte == START);
+                return Err(CommandError::InvalidHeader);
+            }
+            if self.header_len < HEADER_LEN {
+                return Ok(None);
+            }
+
+            let [_, kind, high, low] = self.header;
+            self.expected = u16::from_be_bytes([high, low]) as usize;
+            self.received = 0;
+            let too_large = self.expected > self.buf.as_ref().len();
+            self.skipping = kind != DATA || too_large;
+            return if kind == DATA && too_large {
+                Err(CommandError::BufferTooSmall)
+            } else {
+                Ok(None)
+            };
+        }
+
+        if self.received < self.expected {
+            if !self.skipping {
+                self.buf.as_mut()[self.received] = byte;
+            }
+            self.received += 1;
+            return Ok(None);
+        }
+
+        let skipped = self.skipping;
+        self.reset();
+        if byte != END {
+            Err(CommandError::MalformedPayload)
+        } else if skipped {
+            Ok(None)
+        } else {
+            Ok(Some(Command::Stream(&self.buf.as_ref()[..self.expected])))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tpm2Decoder, END, START};
+    use crate::{Command, CommandError};
+    extern crate std;
+    use std::vec::Vec;
+
+    /// Synthetic data frame laid out by hand from the TPM2 spec, not a capture. Two LEDs, green and white
+    const SPEC_FRAME: [u8; 11] = [0xc9, 0xda, 0x00, 0x06, 0, 255, 0, 255, 255, 255, 0x36];
+
+    /// Synthetic command frame asking for the pixel count, answered by devices that support it
+    const SPEC_COMMAND: [u8; 6] = [0xc9, 0xc0, 0x00, 0x01, 0x0a, 0x36];
+
+    fn decode_all<B: AsRef<[u8]> + AsMut<[u8]>>(
+        decoder: &mut Tpm2Decoder<B>,
+        bytes: &[u8],
+    ) -> Vec<Result<Vec<u8>, CommandError>> {
+        bytes
+            .iter()
+            .filter_map(|byte| match decoder.push(*byte) {
+                Ok(Some(Command::Stream(frame))) => Some(Ok(frame.to_vec())),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn encodes_stream_and_constant() {
+        let mut buf = [0_u8; 16];
+        let cmd = Command::Stream([0_u8, 255, 0, 255, 255, 255].as_ref());
+        assert_eq!(cmd.try_write_tpm2(&mut buf), Ok(11));
+        assert_eq!(buf[..11], SPEC_FRAME);
+        let cmd: Command<&[u8]> = Command::Constant {
+            led_count: 2,
+            colour: (1, 2, 3),
+        };
+        assert_eq!(cmd.try_write_tpm2(&mut buf), Ok(11));
+        assert_eq!(buf[..11], [START, 0xda, 0, 6, 1, 2, 3, 1, 2, 3, END]);
+    }
+
+    #[test]
+    fn given_unsupported_command_encoding_fails() {
+        let mut buf = [0_u8; 16];
+        let pulse: Command<&[u8]> = Command::Pulse {
+            led_count: 1,
+            start: (0, 0, 0),
+            end: (1, 1, 1),
+            frames: 2,
+            period: 100,
+        };
+        assert_eq!(
+            pulse.try_write_tpm2(&mut buf),
+            Err(CommandError::InvalidHeader)
+        );
+        // No room for the end byte
+        let outcome = Command::Stream([0_u8; 12].as_ref()).try_write_tpm2(&mut buf);
+        assert_eq!(outcome, Err(CommandError::BufferTooSmall));
+    }
+
+    #[test]
+    fn decodes_frames_skipping_commands() {
+        let mut decoder = Tpm2Decoder::new([0_u8; 6]);
+        let mut bytes = Vec::from(&[0x00, 0x36][..]);
+        bytes.extend_from_slice(&SPEC_FRAME);
+        bytes.extend_from_slice(&SPEC_COMMAND);
+        bytes.extend_from_slice(&SPEC_FRAME);
+        let frames = decode_all(&mut decoder, &bytes);
+        assert_eq!(
+            frames,
+            [
+                Ok(SPEC_FRAME[4..10].to_vec()),
+                Ok(SPEC_FRAME[4..10].to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn given_bad_frames_resynchronises() {
+        let mut decoder = Tpm2Decoder::new([0_u8; 6]);
+        let mut bytes = Vec::new();
+        // Unknown type, too large, missing end byte
+        bytes.extend_from_slice(&[START, 0x11]);
+        bytes.extend_from_slice(&[START, 0xda, 0, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, END]);
+        bytes.extend_from_slice(&[START, 0xda, 0, 3, 1, 2, 3, 0]);
+        bytes.extend_from_slice(&SPEC_FRAME);
+        let frames = decode_all(&mut decoder, &bytes);
+        assert_eq!(
+            frames,
+            [
+                Err(CommandError::InvalidHeader),
+                Err(CommandError::BufferTooSmall),
+                Err(CommandError::MalformedPayload),
+                Ok(SPEC_FRAME[4..10].to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let pixels: Vec<u8> = (0..30).collect();
+        let mut buf = [0_u8; 64];
+        let len = Command::Stream(&pixels[..])
+            .try_write_tpm2(&mut buf)
+            .unwrap();
+        let mut decoder = Tpm2Decoder::new([0_u8; 30]);
+        assert_eq!(decode_all(&mut decoder, &buf[..len]), [Ok(pixels)]);
+    }
+}